@@ -1,43 +1,135 @@
-use clap::{Parser, Subcommand, ValueEnum};
-use rusqlite::{Connection, Result as SqliteResult};
+use crate::migrations;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "promption", about = "AI Prompt Manager", version)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+
+    /// Database file to use instead of the default library (also: PROMPTION_DB)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+
+    /// Named library to use, kept apart from the default one
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "db")]
+    pub library: Option<String>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Sync selected items to the project configuration
     Sync {
-        /// Comma-separated list of item IDs to sync
-        #[arg(long, value_delimiter = ',')]
-        ids: Vec<String>,
+        #[command(flatten)]
+        select: ItemSelectors,
 
-        /// Target tool format
-        #[arg(long, value_enum, default_value_t = ToolTarget::Antigravity)]
-        target: ToolTarget,
+        /// Target tool format: a built-in tool or a target defined in targets.toml
+        #[arg(long, default_value = "antigravity", value_parser = TargetNameParser)]
+        target: String,
+
+        /// Show what would be written without touching any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Show a unified diff against existing files (implies --dry-run)
+        #[arg(long, default_value_t = false)]
+        diff: bool,
+
+        /// Delete files from earlier syncs to this target that are no longer selected
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+
+        /// Roo/Kilo mode slug: sync the items as instructions for that mode only
+        #[arg(long)]
+        mode: Option<String>,
+    },
+    /// Sync everything declared in the project's .promption.toml
+    Install {
+        /// Project config file
+        #[arg(long, default_value = PROJECT_CONFIG_FILE)]
+        file: PathBuf,
+
+        /// Show what would be written without touching any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Show a unified diff against existing files (implies --dry-run)
+        #[arg(long, default_value_t = false)]
+        diff: bool,
+
+        /// Delete files from earlier syncs that are no longer declared
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+    },
+    /// Remove every file promption synced for a target
+    Clean {
+        /// Target the files were synced to
+        #[arg(long)]
+        target: String,
+
+        /// Show what would be deleted without touching any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Roo/Kilo mode slug the files were synced for
+        #[arg(long)]
+        mode: Option<String>,
     },
     /// List all items in the database
     List {
         /// Filter by item type (skill, rule, workflow)
         #[arg(short, long)]
         r#type: Option<String>,
+
+        /// Filter by tag (repeatable, by name or ID)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Whether items must have any or all of the given tags
+        #[arg(long, value_enum, default_value_t = TagMatch::Any)]
+        tag_mode: TagMatch,
     },
-    /// Sync agent configurations to opencode.json
+    /// Sync agent configurations to opencode.json or another agent target
     SyncAgents {
-        /// Comma-separated list of agent IDs to sync
-        #[arg(long, value_delimiter = ',')]
-        ids: Vec<String>,
+        #[command(flatten)]
+        select: AgentSelectors,
+
+        /// Target tool format
+        #[arg(long, value_enum, default_value_t = AgentTarget::Opencode)]
+        target: AgentTarget,
+
+        /// Show what would be written without touching any files
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Show a unified diff against existing files (implies --dry-run)
+        #[arg(long, default_value_t = false)]
+        diff: bool,
     },
     /// List all agents
     ListAgents,
+    /// Import agents defined in an existing opencode.json
+    ImportAgents {
+        /// OpenCode config file to read
+        #[arg(long, default_value = "opencode.json")]
+        file: PathBuf,
+
+        /// What to do when an agent with the same name already exists
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Skip)]
+        on_conflict: ConflictPolicy,
+
+        /// Show what would be imported without changing the library
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
     /// Create a new agent
     CreateAgent {
         /// Agent name (kebab-case format)
@@ -138,6 +230,300 @@ pub enum Commands {
         #[arg(long)]
         id: String,
     },
+    /// Create a new skill, rule or workflow
+    CreateItem {
+        /// Item name
+        #[arg(long)]
+        name: String,
+
+        /// Item type
+        #[arg(long = "type", value_enum)]
+        item_type: ItemTypeArg,
+
+        /// Item content (inline text, file path if --content-file is set, or "-" for stdin)
+        #[arg(long)]
+        content: String,
+
+        /// Treat content as file path (reads content from file)
+        #[arg(long, default_value_t = false)]
+        content_file: bool,
+
+        #[command(flatten)]
+        metadata: ItemMetadataArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Get item details by ID or name
+    GetItem {
+        /// Item ID or name
+        #[arg(long)]
+        id: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Update an existing item
+    UpdateItem {
+        /// Item ID or name to update
+        #[arg(long)]
+        id: String,
+
+        /// New name (optional)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// New type (optional)
+        #[arg(long = "type", value_enum)]
+        item_type: Option<ItemTypeArg>,
+
+        /// New content (inline text, file path if --content-file is set, or "-" for stdin)
+        #[arg(long)]
+        content: Option<String>,
+
+        /// Treat content as file path
+        #[arg(long, default_value_t = false)]
+        content_file: bool,
+
+        #[command(flatten)]
+        metadata: ItemMetadataArgs,
+    },
+    /// Delete an item
+    DeleteItem {
+        /// Item ID or name to delete
+        #[arg(long)]
+        id: String,
+    },
+    /// Import items from a project's AI config files, or a bundle made by `export`
+    #[command(group(ArgGroup::new("input").required(true).args(["bundle", "from"])))]
+    Import {
+        /// Bundle file (.json or .tar.gz) written by `promption export`
+        bundle: Option<PathBuf>,
+
+        /// Tool layout to read
+        #[arg(long, value_enum)]
+        from: Option<ImportSource>,
+
+        /// Project root to read from (with --from)
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+
+        /// What to do with bundle entries whose name is already taken
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Skip)]
+        on_conflict: ConflictPolicy,
+
+        /// Show what would be imported without changing the library
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Export items with their tags, and agents, to a portable bundle
+    Export {
+        #[command(flatten)]
+        select: ItemSelectors,
+
+        /// Comma-separated agent IDs or names to include
+        #[arg(long, value_delimiter = ',')]
+        agents: Vec<String>,
+
+        /// Include every agent
+        #[arg(long, default_value_t = false)]
+        all_agents: bool,
+
+        /// Output file: .json, or .tar.gz / .tgz for a compressed archive
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Create the database, or upgrade its schema (every command also does this)
+    Init,
+    /// List named libraries (select one with --library NAME)
+    Libraries,
+    /// Report drift between the library and files recorded in .promption.lock
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Full-text search over items and agent prompts
+    Search {
+        /// Search terms (matched as prefixes, all terms must appear)
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Filter by type (skill, rule, workflow, agent)
+        #[arg(short, long)]
+        r#type: Option<String>,
+
+        /// Only include items with this tag (repeatable, by name or ID)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Whether items must have any or all of the given tags
+        #[arg(long, value_enum, default_value_t = TagMatch::Any)]
+        tag_mode: TagMatch,
+
+        /// Maximum number of results
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Pass the query to SQLite FTS5 as-is (supports OR, NOT, "phrases", NEAR)
+        #[arg(long, default_value_t = false)]
+        raw: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Manage tags
+    Tags {
+        #[command(subcommand)]
+        action: TagCommands,
+    },
+    /// Manage item tag assignments
+    Items {
+        #[command(subcommand)]
+        action: ItemCommands,
+    },
+}
+
+/// Ways of picking items; every selector adds to the set, `--tag` then narrows it
+/// (or selects by tag on its own)
+#[derive(Args, Debug, Default)]
+pub struct ItemSelectors {
+    /// Comma-separated list of item IDs
+    #[arg(long, value_delimiter = ',')]
+    pub ids: Vec<String>,
+
+    /// Comma-separated list of item names (case-insensitive)
+    #[arg(long, value_delimiter = ',')]
+    pub names: Vec<String>,
+
+    /// Name glob such as 'react-*', matched against names and slugs (repeatable)
+    #[arg(long = "match")]
+    pub patterns: Vec<String>,
+
+    /// Select every item of this type
+    #[arg(long = "type", value_enum)]
+    pub item_type: Option<ItemTypeArg>,
+
+    /// Select every item in the library
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+
+    /// Only items with this tag (repeatable, by name or ID)
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+
+    /// Whether items must have any or all of the given tags
+    #[arg(long, value_enum, default_value_t = TagMatch::Any)]
+    pub tag_mode: TagMatch,
+}
+
+impl ItemSelectors {
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+            && self.names.is_empty()
+            && self.patterns.is_empty()
+            && self.item_type.is_none()
+            && !self.all
+            && self.tags.is_empty()
+    }
+}
+
+#[derive(Args, Debug, Default)]
+pub struct AgentSelectors {
+    /// Comma-separated list of agent IDs
+    #[arg(long, value_delimiter = ',')]
+    pub ids: Vec<String>,
+
+    /// Comma-separated list of agent names
+    #[arg(long, value_delimiter = ',')]
+    pub names: Vec<String>,
+
+    /// Name glob such as 'review-*' (repeatable)
+    #[arg(long = "match")]
+    pub patterns: Vec<String>,
+
+    /// Select every agent in the library
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+}
+
+impl AgentSelectors {
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.names.is_empty() && self.patterns.is_empty() && !self.all
+    }
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// List all tags
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Create a new tag
+    Create {
+        /// Tag name
+        #[arg(long)]
+        name: String,
+
+        /// Tag color (#RRGGBB or #RRGGBBAA)
+        #[arg(long, default_value = DEFAULT_TAG_COLOR)]
+        color: String,
+    },
+    /// Delete a tag (system tags cannot be deleted)
+    Delete {
+        /// Tag ID or name
+        #[arg(long)]
+        id: String,
+    },
+    /// Rename a tag
+    Rename {
+        /// Tag ID or name
+        #[arg(long)]
+        id: String,
+
+        /// New tag name
+        #[arg(long)]
+        name: String,
+    },
+    /// Change a tag's color
+    Recolor {
+        /// Tag ID or name
+        #[arg(long)]
+        id: String,
+
+        /// New color (#RRGGBB or #RRGGBBAA)
+        #[arg(long)]
+        color: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ItemCommands {
+    /// Add tags to an item
+    Tag {
+        /// Item ID or name
+        #[arg(long)]
+        id: String,
+
+        /// Comma-separated list of tag names or IDs
+        #[arg(long, value_delimiter = ',', required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from an item
+    Untag {
+        /// Item ID or name
+        #[arg(long)]
+        id: String,
+
+        /// Comma-separated list of tag names or IDs
+        #[arg(long, value_delimiter = ',', required = true)]
+        tags: Vec<String>,
+    },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -148,6 +534,50 @@ pub enum ToolTarget {
     Opencode,
     Cline,
     Copilot,
+    Claude,
+    AgentsMd,
+    Gemini,
+    Continue,
+    Aider,
+    Roo,
+    Kilo,
+    Zed,
+    Jetbrains,
+    Kiro,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Default)]
+pub enum AgentTarget {
+    /// opencode.json agent entries
+    #[default]
+    Opencode,
+    /// .claude/agents/<name>.md subagent files
+    Claude,
+    /// Roo Code custom modes in .roomodes
+    Roo,
+    /// Kilo Code custom modes in .kilocodemodes
+    Kilo,
+}
+
+impl AgentTarget {
+    /// Target name recorded in .promption.lock
+    fn lock_name(&self) -> &str {
+        match self {
+            AgentTarget::Opencode => "opencode-agents",
+            AgentTarget::Claude => "claude-agents",
+            AgentTarget::Roo => "roo-agents",
+            AgentTarget::Kilo => "kilo-agents",
+        }
+    }
+
+    fn destination(&self) -> &str {
+        match self {
+            AgentTarget::Opencode => "opencode.json",
+            AgentTarget::Claude => ".claude/agents/",
+            AgentTarget::Roo => ".roomodes",
+            AgentTarget::Kilo => ".kilocodemodes",
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
@@ -165,6 +595,55 @@ impl AgentModeArg {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemTypeArg {
+    Skill,
+    Rule,
+    Workflow,
+}
+
+impl ItemTypeArg {
+    fn as_str(&self) -> &str {
+        match self {
+            ItemTypeArg::Skill => "skill",
+            ItemTypeArg::Rule => "rule",
+            ItemTypeArg::Workflow => "workflow",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// How `import-agents` treats an agent whose name is already taken
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Default)]
+pub enum ConflictPolicy {
+    /// Keep the existing agent
+    #[default]
+    Skip,
+    /// Replace the existing agent's settings
+    Overwrite,
+    /// Import under a free name (reviewer-2, "React Rules 2", ...)
+    Rename,
+}
+
+/// Project layouts `import` can read
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ImportSource {
+    Cursor,
+    Windsurf,
+    Cline,
+    Opencode,
+    Antigravity,
+    Copilot,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
 pub enum OutputFormat {
     Text,
@@ -177,76 +656,418 @@ struct Item {
     name: String,
     content: String,
     item_type: String,
+    metadata: ItemMetadata,
 }
 
-#[derive(Debug)]
-struct Agent {
-    id: String,
-    name: String,
-    mode: String,
-    model: Option<String>,
-    prompt_content: Option<String>,
-    tools_config: Option<String>,
-    permissions_config: Option<String>,
+/// When a tool should load an item
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    /// Loaded in every conversation
+    Always,
+    /// Loaded when files matching the item's globs are in context
+    Glob,
+    /// Loaded when the agent judges the item's description relevant
+    Model,
+    /// Only loaded when referenced explicitly
+    Manual,
 }
 
-fn get_db_path() -> PathBuf {
-    dirs::config_dir()
-        .expect("Could not find config directory")
-        .join("com.abdssamie.promption")
-        .join("promption.db")
+impl Activation {
+    fn as_str(&self) -> &str {
+        match self {
+            Activation::Always => "always",
+            Activation::Glob => "glob",
+            Activation::Model => "model",
+            Activation::Manual => "manual",
+        }
+    }
 }
 
-fn slugify(name: &str) -> String {
-    let sanitized: String = name
-        .replace(['\\', '/'], "-")
-        .to_lowercase()
-        .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '-' })
-        .collect();
-
-    // Remove leading/trailing dashes and collapse multiple dashes
-    let result: String = sanitized
-        .split('-')
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<_>>()
-        .join("-");
+/// Sync hints stored as JSON in `items.metadata`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct ItemMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activation: Option<Activation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    globs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Overrides keyed by target name; unset fields fall back to the values above
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    targets: BTreeMap<String, ItemMetadata>,
+}
 
-    if result.is_empty() {
-        "unnamed".to_string()
-    } else {
-        result
+impl ItemMetadata {
+    /// Unreadable JSON is treated as no metadata rather than failing every query
+    fn parse(json: Option<String>) -> Self {
+        json.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default()
     }
-}
 
-fn validate_agent_name(name: &str) -> Result<(), String> {
-    let kebab_case_regex = regex::Regex::new(r"^[a-z0-9]+(-[a-z0-9]+)*$").unwrap();
-    if !kebab_case_regex.is_match(name) {
-        return Err("Agent name must be in kebab-case format (lowercase letters, numbers, and hyphens only)".to_string());
+    fn is_empty(&self) -> bool {
+        *self == ItemMetadata::default()
     }
-    if name.len() > 255 {
-        return Err("Agent name exceeds maximum length of 255 characters".to_string());
+
+    /// Explicit activation, else glob-scoped when globs are set
+    fn activation(&self) -> Option<Activation> {
+        self.activation
+            .or_else(|| (!self.globs.is_empty()).then_some(Activation::Glob))
     }
-    Ok(())
-}
 
-fn parse_tools(tools: &[String]) -> SqliteResult<Option<String>> {
-    if tools.is_empty() {
-        return Ok(None);
+    /// Activation to emit, `default` when unset. Glob activation without globs
+    /// cannot be expressed, so it falls back to always with a warning.
+    fn effective_activation(&self, name: &str, default: Activation) -> Activation {
+        match self.activation().unwrap_or(default) {
+            Activation::Glob if self.globs.is_empty() => {
+                eprintln!("Warning: '{}' is glob-activated but has no globs, loading it always", name);
+                Activation::Always
+            }
+            activation => activation,
+        }
     }
-    
-    let mut tools_map = serde_json::Map::new();
-    for tool in tools {
-        tools_map.insert(tool.clone(), serde_json::Value::Bool(true));
+
+    /// Metadata as seen by one target, with its overrides applied
+    fn for_target(&self, target: ToolTarget) -> ItemMetadata {
+        self.for_target_name(&target_name(target))
     }
-    
-    serde_json::to_string(&tools_map)
-        .map(Some)
-        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
-}
 
-fn parse_permissions(permissions: &[String]) -> SqliteResult<Option<String>> {
-    if permissions.is_empty() {
+    fn for_target_name(&self, target: &str) -> ItemMetadata {
+        let mut resolved = ItemMetadata {
+            targets: BTreeMap::new(),
+            ..self.clone()
+        };
+        if let Some(overrides) = self.targets.get(target) {
+            if overrides.activation.is_some() {
+                resolved.activation = overrides.activation;
+            }
+            if !overrides.globs.is_empty() {
+                resolved.globs = overrides.globs.clone();
+            }
+            if overrides.description.is_some() {
+                resolved.description = overrides.description.clone();
+            }
+        }
+        resolved
+    }
+
+    /// Description for frontmatter, falling back to the item name
+    fn description_or<'a>(&'a self, name: &'a str) -> &'a str {
+        self.description.as_deref().unwrap_or(name)
+    }
+}
+
+/// Metadata flags shared by create-item and update-item
+#[derive(Args, Debug, Default)]
+pub struct ItemMetadataArgs {
+    /// When tools should load the item
+    #[arg(long, value_enum)]
+    pub activation: Option<Activation>,
+
+    /// Comma-separated file globs the item applies to (pass "" to clear)
+    #[arg(long, value_delimiter = ',')]
+    pub globs: Option<Vec<String>>,
+
+    /// Short description tools use to decide when to load the item (pass "" to clear)
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Set the metadata above for one target only
+    #[arg(long = "for", value_parser = TargetNameParser)]
+    pub for_target: Option<String>,
+}
+
+impl ItemMetadataArgs {
+    fn apply(&self, metadata: &mut ItemMetadata) -> Result<(), String> {
+        let key = match &self.for_target {
+            Some(name) => Some(SyncTarget::resolve(name)?.name()),
+            None => None,
+        };
+        let fields = match &key {
+            Some(key) => metadata.targets.entry(key.clone()).or_default(),
+            None => &mut *metadata,
+        };
+
+        if let Some(activation) = self.activation {
+            fields.activation = Some(activation);
+        }
+        if let Some(globs) = &self.globs {
+            fields.globs = globs.iter().map(|g| g.trim().to_string()).filter(|g| !g.is_empty()).collect();
+        }
+        if let Some(description) = &self.description {
+            let description = description.trim();
+            fields.description = (!description.is_empty()).then(|| description.to_string());
+        }
+
+        if let Some(key) = key {
+            if metadata.targets.get(&key).is_some_and(ItemMetadata::is_empty) {
+                metadata.targets.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_set(&self) -> bool {
+        self.activation.is_some() || self.globs.is_some() || self.description.is_some()
+    }
+}
+
+#[derive(Debug)]
+struct Tag {
+    id: String,
+    name: String,
+    color: String,
+    is_system: bool,
+}
+
+#[derive(Debug)]
+struct SearchHit {
+    id: String,
+    entity_type: String,
+    name: String,
+    snippet: String,
+    score: f64,
+}
+
+#[derive(Debug)]
+struct Agent {
+    id: String,
+    name: String,
+    mode: String,
+    model: Option<String>,
+    prompt_content: Option<String>,
+    tools_config: Option<String>,
+    permissions_config: Option<String>,
+}
+
+/// Environment variable naming the database file, overridden by --db and --library
+const DB_ENV: &str = "PROMPTION_DB";
+/// Directory under the app dir holding named libraries as <name>.db
+const LIBRARIES_DIR: &str = "libraries";
+
+/// Database picked for the GUI when it was launched with --db, --library or PROMPTION_DB
+static GUI_DB_PATH: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+fn get_app_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("com.abdssamie.promption"))
+        .ok_or_else(|| format!("Could not find config directory; use --db or set {}", DB_ENV))
+}
+
+fn validate_library_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid library name '{}': use lowercase letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn get_library_path(name: &str) -> Result<PathBuf, String> {
+    validate_library_name(name)?;
+    Ok(get_app_dir()?.join(LIBRARIES_DIR).join(format!("{}.db", name)))
+}
+
+fn get_db_path(db: Option<&Path>, library: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(db) = db {
+        return Ok(db.to_path_buf());
+    }
+    if let Some(library) = library {
+        return get_library_path(library);
+    }
+    match std::env::var_os(DB_ENV) {
+        Some(db) if !db.is_empty() => Ok(PathBuf::from(db)),
+        _ => Ok(get_app_dir()?.join("promption.db")),
+    }
+}
+
+/// Names of the libraries under the app dir; the default library and files that
+/// could not be opened with --library are not included
+pub fn list_libraries() -> Result<Vec<String>, String> {
+    let dir = get_app_dir()?.join(LIBRARIES_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Could not read {}: {}", dir.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "db" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(str::to_string)
+        })
+        .filter(|name| validate_library_name(name).is_ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Named library the GUI was launched on, if any
+pub fn gui_library() -> Option<String> {
+    let path = GUI_DB_PATH.get()?;
+    let libraries = get_app_dir().ok()?.join(LIBRARIES_DIR);
+    if path.parent()? != libraries {
+        return None;
+    }
+    path.file_stem()?.to_str().map(str::to_string)
+}
+
+/// Connection URL for the GUI's SQL plugin. Relative URLs resolve inside the
+/// app dir, so the default library keeps the URL it has always had.
+pub fn gui_database_url() -> String {
+    match GUI_DB_PATH.get() {
+        Some(path) => format!("sqlite:{}", path.display()),
+        None => "sqlite:promption.db".to_string(),
+    }
+}
+
+fn slugify(name: &str) -> String {
+    let sanitized: String = name
+        .replace(['\\', '/'], "-")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+
+    // Remove leading/trailing dashes and collapse multiple dashes
+    let result: String = sanitized
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if result.is_empty() {
+        "unnamed".to_string()
+    } else {
+        result
+    }
+}
+
+/// Minimal glob matcher supporting `*` and `?`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = backtrack {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            backtrack = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+fn name_matches_glob(name: &str, pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    glob_match(&pattern, &name.to_lowercase()) || glob_match(&pattern, &slugify(name))
+}
+
+fn validate_agent_name(name: &str) -> Result<(), String> {
+    let kebab_case_regex = regex::Regex::new(r"^[a-z0-9]+(-[a-z0-9]+)*$").unwrap();
+    if !kebab_case_regex.is_match(name) {
+        return Err("Agent name must be in kebab-case format (lowercase letters, numbers, and hyphens only)".to_string());
+    }
+    if name.len() > 255 {
+        return Err("Agent name exceeds maximum length of 255 characters".to_string());
+    }
+    Ok(())
+}
+
+// Same limits the GUI enforces in services/database.ts
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_CONTENT_LENGTH: usize = 1_000_000;
+const MAX_TAG_NAME_LENGTH: usize = 50;
+const DEFAULT_TAG_COLOR: &str = "#6366f1";
+
+fn validate_item_input(name: Option<&str>, content: Option<&str>) -> Result<(), String> {
+    if let Some(name) = name {
+        if name.trim().is_empty() {
+            return Err("Name cannot be empty".to_string());
+        }
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(format!("Name exceeds maximum length of {} characters", MAX_NAME_LENGTH));
+        }
+    }
+    if let Some(content) = content {
+        if content.trim().is_empty() {
+            return Err("Content cannot be empty".to_string());
+        }
+        if content.len() > MAX_CONTENT_LENGTH {
+            return Err(format!("Content exceeds maximum length of {} characters", MAX_CONTENT_LENGTH));
+        }
+    }
+    Ok(())
+}
+
+fn validate_tag_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Tag name cannot be empty".to_string());
+    }
+    if name.len() > MAX_TAG_NAME_LENGTH {
+        return Err(format!("Tag name exceeds maximum length of {} characters", MAX_TAG_NAME_LENGTH));
+    }
+    Ok(())
+}
+
+fn validate_tag_color(color: &str) -> Result<(), String> {
+    let color_regex = regex::Regex::new(r"^#[0-9A-Fa-f]{6}([0-9A-Fa-f]{2})?$").unwrap();
+    if !color_regex.is_match(color) {
+        return Err("Invalid color format. Use #RRGGBB or #RRGGBBAA".to_string());
+    }
+    Ok(())
+}
+
+/// Resolve a content argument: "-" reads stdin, otherwise the value is either
+/// inline text or, when `from_file` is set, a path to read.
+fn read_content_arg(value: &str, from_file: bool) -> std::io::Result<String> {
+    if value == "-" {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        Ok(buf)
+    } else if from_file {
+        fs::read_to_string(value)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+fn parse_tools(tools: &[String]) -> SqliteResult<Option<String>> {
+    if tools.is_empty() {
+        return Ok(None);
+    }
+    
+    let mut tools_map = serde_json::Map::new();
+    for tool in tools {
+        tools_map.insert(tool.clone(), serde_json::Value::Bool(true));
+    }
+    
+    serde_json::to_string(&tools_map)
+        .map(Some)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn parse_permissions(permissions: &[String]) -> SqliteResult<Option<String>> {
+    if permissions.is_empty() {
         return Ok(None);
     }
     
@@ -274,6 +1095,16 @@ fn parse_permissions(permissions: &[String]) -> SqliteResult<Option<String>> {
         .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
 }
 
+fn item_from_row(row: &rusqlite::Row) -> SqliteResult<Item> {
+    Ok(Item {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        content: row.get(2)?,
+        item_type: row.get(3)?,
+        metadata: ItemMetadata::parse(row.get(4)?),
+    })
+}
+
 fn get_items_by_ids(conn: &Connection, ids: &[String]) -> SqliteResult<Vec<Item>> {
     if ids.is_empty() {
         return Ok(vec![]);
@@ -281,414 +1112,2401 @@ fn get_items_by_ids(conn: &Connection, ids: &[String]) -> SqliteResult<Vec<Item>
 
     let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
     let query = format!(
-        "SELECT id, name, content, item_type FROM items WHERE id IN ({})",
+        "SELECT id, name, content, item_type, metadata FROM items WHERE id IN ({})",
         placeholders.join(", ")
     );
 
     let mut stmt = conn.prepare(&query)?;
     let params: Vec<&dyn rusqlite::ToSql> = ids.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
 
-    let items = stmt.query_map(params.as_slice(), |row| {
-        Ok(Item {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            content: row.get(2)?,
-            item_type: row.get(3)?,
-        })
-    })?;
+    let items = stmt.query_map(params.as_slice(), item_from_row)?;
+
+    items.collect()
+}
+
+fn get_items_by_names(conn: &Connection, names: &[String]) -> SqliteResult<Vec<Item>> {
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let placeholders: Vec<String> = names.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id, name, content, item_type, metadata FROM items WHERE name COLLATE NOCASE IN ({}) ORDER BY name",
+        placeholders.join(", ")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let params: Vec<&dyn rusqlite::ToSql> = names.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+
+    let items = stmt.query_map(params.as_slice(), item_from_row)?;
 
     items.collect()
 }
 
+/// Items whose name or slug matches the glob (case-insensitive)
+fn get_items_matching(conn: &Connection, pattern: &str) -> SqliteResult<Vec<Item>> {
+    let items = get_all_items(conn, None)?;
+    Ok(items
+        .into_iter()
+        .filter(|item| name_matches_glob(&item.name, pattern))
+        .collect())
+}
+
 fn get_all_items(conn: &Connection, type_filter: Option<&str>) -> SqliteResult<Vec<Item>> {
     let query = match type_filter {
-        Some(_) => "SELECT id, name, content, item_type FROM items WHERE item_type = ? ORDER BY updated_at DESC",
-        None => "SELECT id, name, content, item_type FROM items ORDER BY updated_at DESC",
+        Some(_) => "SELECT id, name, content, item_type, metadata FROM items WHERE item_type = ? ORDER BY updated_at DESC",
+        None => "SELECT id, name, content, item_type, metadata FROM items ORDER BY updated_at DESC",
     };
 
     let mut stmt = conn.prepare(query)?;
 
     let rows: Vec<SqliteResult<Item>> = if let Some(t) = type_filter {
-        stmt.query_map([t], |row| {
-            Ok(Item {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                content: row.get(2)?,
-                item_type: row.get(3)?,
-            })
-        })?
-        .collect()
+        stmt.query_map([t], item_from_row)?.collect()
     } else {
-        stmt.query_map([], |row| {
-            Ok(Item {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                content: row.get(2)?,
-                item_type: row.get(3)?,
-            })
-        })?
-        .collect()
+        stmt.query_map([], item_from_row)?.collect()
     };
 
     rows.into_iter().collect()
 }
 
-fn sync_items(items: &[Item], target: ToolTarget) -> std::io::Result<()> {
-    match target {
-        ToolTarget::Antigravity => sync_antigravity(items),
-        ToolTarget::Cursor => sync_cursor(items),
-        ToolTarget::Windsurf => sync_windsurf(items),
-        ToolTarget::Opencode => sync_opencode(items),
-        ToolTarget::Cline => sync_cline(items),
-        ToolTarget::Copilot => sync_copilot(items),
-    }
+fn tag_from_row(row: &rusqlite::Row) -> SqliteResult<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        is_system: row.get::<_, i64>(3)? != 0,
+    })
 }
 
-fn sync_antigravity(items: &[Item]) -> std::io::Result<()> {
-    let agent_path = PathBuf::from(".agent");
-    let skills_path = agent_path.join("skills");
-    let rules_path = agent_path.join("rules");
-    let workflows_path = agent_path.join("workflows");
+fn get_all_tags(conn: &Connection) -> SqliteResult<Vec<Tag>> {
+    let query = "SELECT id, name, color, is_system FROM tags ORDER BY is_system DESC, name";
+    let mut stmt = conn.prepare(query)?;
+    let tags = stmt.query_map([], tag_from_row)?;
+    tags.collect()
+}
 
-    fs::create_dir_all(&skills_path)?;
-    fs::create_dir_all(&rules_path)?;
-    fs::create_dir_all(&workflows_path)?;
+fn get_tag_by_id_or_name(conn: &Connection, id_or_name: &str) -> SqliteResult<Option<Tag>> {
+    let query = "SELECT id, name, color, is_system FROM tags WHERE id = ? OR name = ? COLLATE NOCASE";
+    let mut stmt = conn.prepare(query)?;
+    let mut tags = stmt.query_map([id_or_name, id_or_name], tag_from_row)?;
+    tags.next().transpose()
+}
 
-    for item in items {
-        let slug = slugify(&item.name);
-        match item.item_type.as_str() {
-            "skill" => {
-                let skill_dir = skills_path.join(&slug);
-                fs::create_dir_all(&skill_dir)?;
-                fs::write(skill_dir.join("SKILL.md"), &item.content)?;
-                println!("  + .agent/skills/{}/SKILL.md", slug);
-            }
-            "rule" => {
-                fs::write(rules_path.join(format!("{}.md", slug)), &item.content)?;
-                println!("  + .agent/rules/{}.md", slug);
+fn get_item_tags(conn: &Connection, item_id: &str) -> SqliteResult<Vec<Tag>> {
+    let query = "SELECT t.id, t.name, t.color, t.is_system FROM tags t \
+                 INNER JOIN item_tags it ON t.id = it.tag_id \
+                 WHERE it.item_id = ? ORDER BY t.name";
+    let mut stmt = conn.prepare(query)?;
+    let tags = stmt.query_map([item_id], tag_from_row)?;
+    tags.collect()
+}
+
+/// Look up every tag by ID or name, failing on the first one that doesn't exist
+fn resolve_tags(conn: &Connection, ids_or_names: &[String]) -> SqliteResult<Vec<Tag>> {
+    let mut tags: Vec<Tag> = Vec::new();
+    for id_or_name in ids_or_names {
+        match get_tag_by_id_or_name(conn, id_or_name)? {
+            Some(tag) => {
+                if !tags.iter().any(|t| t.id == tag.id) {
+                    tags.push(tag);
+                }
             }
-            "workflow" => {
-                fs::write(workflows_path.join(format!("{}.md", slug)), &item.content)?;
-                println!("  + .agent/workflows/{}.md", slug);
+            None => {
+                eprintln!("Error: Tag '{}' not found", id_or_name);
+                return Err(rusqlite::Error::QueryReturnedNoRows);
             }
-            _ => {}
         }
     }
-    Ok(())
+    Ok(tags)
 }
 
-fn sync_cursor(items: &[Item]) -> std::io::Result<()> {
-    let rules_path = PathBuf::from(".cursor/rules");
-    fs::create_dir_all(&rules_path)?;
+/// IDs of items carrying any (or all) of the given tags
+fn get_item_ids_with_tags(
+    conn: &Connection,
+    tags: &[String],
+    mode: TagMatch,
+) -> SqliteResult<HashSet<String>> {
+    let tags = resolve_tags(conn, tags)?;
 
-    for item in items {
-        let slug = slugify(&item.name);
-        // Cursor uses .mdc for rules with frontmatter
-        if item.item_type == "rule" {
-            let content = format!(
-                "---\ndescription: {}\nglobs: *\n---\n\n{}",
-                item.name, item.content
-            );
-            fs::write(rules_path.join(format!("{}.mdc", slug)), content)?;
-            println!("  + .cursor/rules/{}.mdc", slug);
-        } else {
-            // Treat skills/workflows as regular markdown docs for context
-            fs::write(rules_path.join(format!("{}.md", slug)), &item.content)?;
-            println!("  + .cursor/rules/{}.md", slug);
-        }
+    let placeholders: Vec<String> = tags.iter().map(|_| "?".to_string()).collect();
+    let required = match mode {
+        TagMatch::Any => 1,
+        TagMatch::All => tags.len(),
+    };
+    let query = format!(
+        "SELECT item_id FROM item_tags WHERE tag_id IN ({}) GROUP BY item_id HAVING COUNT(DISTINCT tag_id) >= {}",
+        placeholders.join(", "),
+        required
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let params: Vec<&dyn rusqlite::ToSql> = tags.iter().map(|t| &t.id as &dyn rusqlite::ToSql).collect();
+    let ids = stmt.query_map(params.as_slice(), |row| row.get(0))?;
+    ids.collect()
+}
+
+fn filter_items_by_tags(
+    conn: &Connection,
+    items: Vec<Item>,
+    tags: &[String],
+    mode: TagMatch,
+) -> SqliteResult<Vec<Item>> {
+    if tags.is_empty() {
+        return Ok(items);
     }
-    Ok(())
+
+    let matching = get_item_ids_with_tags(conn, tags, mode)?;
+    Ok(items.into_iter().filter(|item| matching.contains(&item.id)).collect())
 }
 
-fn sync_windsurf(items: &[Item]) -> std::io::Result<()> {
-    let rules_path = PathBuf::from(".windsurf/rules");
-    let skills_path = PathBuf::from(".windsurf/skills");
-    fs::create_dir_all(&rules_path)?;
-    fs::create_dir_all(&skills_path)?;
+/// Turn free text into an FTS5 query: every term is quoted (so punctuation like
+/// `react-hooks` is not parsed as syntax) and matched as a prefix.
+fn build_fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    for item in items {
-        let slug = slugify(&item.name);
-        match item.item_type.as_str() {
-            "skill" => {
-                let skill_dir = skills_path.join(&slug);
-                fs::create_dir_all(&skill_dir)?;
-                let content = format!(
-                    "---\nname: {}\ndescription: {}\n---\n\n{}",
-                    slug, item.name, item.content
-                );
-                fs::write(skill_dir.join("SKILL.md"), content)?;
-                println!("  + .windsurf/skills/{}/SKILL.md", slug);
-            }
-            _ => {
-                // Rules and Workflows go to rules/
-                fs::write(rules_path.join(format!("{}.md", slug)), &item.content)?;
-                println!("  + .windsurf/rules/{}.md", slug);
-            }
-        }
+fn search_library(
+    conn: &Connection,
+    fts_query: &str,
+    type_filter: Option<&str>,
+    highlight: (&str, &str),
+) -> SqliteResult<Vec<SearchHit>> {
+    // Name matches weigh ten times more than content matches; bm25 is lower-is-better
+    let mut query = "SELECT entity_id, entity_type, name, \
+                     snippet(search_index, -1, ?, ?, '…', 16), \
+                     bm25(search_index, 0.0, 0.0, 10.0, 1.0) AS score \
+                     FROM search_index WHERE search_index MATCH ?"
+        .to_string();
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&highlight.0, &highlight.1, &fts_query];
+
+    if let Some(t) = &type_filter {
+        query.push_str(" AND entity_type = ?");
+        params.push(t);
     }
-    Ok(())
+    query.push_str(" ORDER BY score");
+
+    let mut stmt = conn.prepare(&query)?;
+    let hits = stmt.query_map(params.as_slice(), |row| {
+        Ok(SearchHit {
+            id: row.get(0)?,
+            entity_type: row.get(1)?,
+            name: row.get(2)?,
+            snippet: row.get(3)?,
+            score: row.get(4)?,
+        })
+    })?;
+
+    hits.collect()
 }
 
-fn sync_opencode(items: &[Item]) -> std::io::Result<()> {
-    let rules_path = PathBuf::from(".opencode/rules");
-    let skills_path = PathBuf::from(".opencode/skills");
-    fs::create_dir_all(&rules_path)?;
-    fs::create_dir_all(&skills_path)?;
+const PROJECT_CONFIG_FILE: &str = ".promption.toml";
 
-    for item in items {
-        let slug = slugify(&item.name);
-        match item.item_type.as_str() {
-            "skill" => {
-                let skill_dir = skills_path.join(&slug);
-                fs::create_dir_all(&skill_dir)?;
-                let content = format!(
-                    "---\nname: {}\ndescription: {}\n---\n\n{}",
-                    slug, item.name, item.content
-                );
-                fs::write(skill_dir.join("SKILL.md"), content)?;
-                println!("  + .opencode/skills/{}/SKILL.md", slug);
-            }
-            _ => {
-                fs::write(rules_path.join(format!("{}.md", slug)), &item.content)?;
-                println!("  + .opencode/rules/{}.md", slug);
-            }
+/// `.promption.toml`: the items, agents and targets a project uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectConfig {
+    #[serde(default)]
+    targets: Vec<String>,
+    #[serde(default)]
+    prune: bool,
+    #[serde(default)]
+    items: ProjectItems,
+    #[serde(default)]
+    agents: ProjectAgents,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProjectItems {
+    ids: Vec<String>,
+    names: Vec<String>,
+    #[serde(rename = "match")]
+    patterns: Vec<String>,
+    types: Vec<ItemTypeArg>,
+    tags: Vec<String>,
+    tag_mode: TagMatch,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProjectAgents {
+    targets: Vec<String>,
+    ids: Vec<String>,
+    names: Vec<String>,
+    #[serde(rename = "match")]
+    patterns: Vec<String>,
+    all: bool,
+}
+
+impl ProjectConfig {
+    fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Could not parse {}: {}", path.display(), e))
+    }
+
+    fn targets(&self) -> Result<Vec<SyncTarget>, String> {
+        self.targets.iter().map(|name| SyncTarget::resolve(name)).collect()
+    }
+
+    /// Agent targets from `[agents].targets`, defaulting to opencode
+    fn agent_targets(&self) -> Result<Vec<AgentTarget>, String> {
+        if self.agents.targets.is_empty() {
+            return Ok(vec![AgentTarget::Opencode]);
+        }
+        self.agents
+            .targets
+            .iter()
+            .map(|name| AgentTarget::from_str(name, true).map_err(|_| format!("Unknown agent target '{}'", name)))
+            .collect()
+    }
+
+    /// Every list in `[items]` adds to the selection, unlike `sync` where
+    /// `--tag` narrows the other selectors.
+    fn item_selectors(&self) -> Vec<ItemSelectors> {
+        let items = &self.items;
+        let mut selectors = vec![ItemSelectors {
+            ids: items.ids.clone(),
+            names: items.names.clone(),
+            patterns: items.patterns.clone(),
+            ..Default::default()
+        }];
+        selectors.extend(items.types.iter().map(|t| ItemSelectors {
+            item_type: Some(*t),
+            ..Default::default()
+        }));
+        if !items.tags.is_empty() {
+            selectors.push(ItemSelectors {
+                tags: items.tags.clone(),
+                tag_mode: items.tag_mode,
+                ..Default::default()
+            });
+        }
+        selectors.retain(|s| !s.is_empty());
+        selectors
+    }
+
+    fn agent_selectors(&self) -> AgentSelectors {
+        AgentSelectors {
+            ids: self.agents.ids.clone(),
+            names: self.agents.names.clone(),
+            patterns: self.agents.patterns.clone(),
+            all: self.agents.all,
         }
     }
-    Ok(())
 }
 
-fn sync_cline(items: &[Item]) -> std::io::Result<()> {
-    let rules_path = PathBuf::from(".clinerules");
-    let skills_path = PathBuf::from(".cline/skills");
-    fs::create_dir_all(&rules_path)?;
-    fs::create_dir_all(&skills_path)?;
+/// Resolve item selectors into a de-duplicated list of items, along with a
+/// description of every selector that matched nothing.
+fn resolve_item_selectors(conn: &Connection, select: &ItemSelectors) -> SqliteResult<(Vec<Item>, Vec<String>)> {
+    let mut unmatched: Vec<String> = Vec::new();
+    let mut found: Vec<Vec<Item>> = Vec::new();
 
-    for item in items {
-        let slug = slugify(&item.name);
-        match item.item_type.as_str() {
-            "skill" => {
-                let skill_dir = skills_path.join(&slug);
-                fs::create_dir_all(&skill_dir)?;
-                let content = format!(
-                    "---\nname: {}\ndescription: {}\n---\n\n{}",
-                    slug, item.name, item.content
-                );
-                fs::write(skill_dir.join("SKILL.md"), content)?;
-                println!("  + .cline/skills/{}/SKILL.md", slug);
+    let tags_only = select.ids.is_empty()
+        && select.names.is_empty()
+        && select.patterns.is_empty()
+        && select.item_type.is_none();
+
+    if select.all || tags_only {
+        // --all, or --tag on its own: start from the whole library
+        found.push(get_all_items(conn, None)?);
+    } else {
+        let by_id = get_items_by_ids(conn, &select.ids)?;
+        for id in &select.ids {
+            if !by_id.iter().any(|item| &item.id == id) {
+                unmatched.push(format!("--ids {}", id));
             }
-            _ => {
-                fs::write(rules_path.join(format!("{}.md", slug)), &item.content)?;
-                println!("  + .clinerules/{}.md", slug);
+        }
+        found.push(by_id);
+
+        let by_name = get_items_by_names(conn, &select.names)?;
+        for name in &select.names {
+            if !by_name.iter().any(|item| item.name.to_lowercase() == name.to_lowercase()) {
+                unmatched.push(format!("--names '{}'", name));
             }
         }
-    }
-    Ok(())
-}
+        found.push(by_name);
+
+        for pattern in &select.patterns {
+            let matched = get_items_matching(conn, pattern)?;
+            if matched.is_empty() {
+                unmatched.push(format!("--match '{}'", pattern));
+            }
+            found.push(matched);
+        }
 
-fn sync_copilot(items: &[Item]) -> std::io::Result<()> {
-    let github_path = PathBuf::from(".github");
-    fs::create_dir_all(&github_path)?;
-    let instructions_path = github_path.join("copilot-instructions.md");
+        if let Some(item_type) = select.item_type {
+            let matched = get_all_items(conn, Some(item_type.as_str()))?;
+            if matched.is_empty() {
+                unmatched.push(format!("--type {}", item_type.as_str()));
+            }
+            found.push(matched);
+        }
+    }
 
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&instructions_path)?;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut items: Vec<Item> = found
+        .into_iter()
+        .flatten()
+        .filter(|item| seen.insert(item.id.clone()))
+        .collect();
 
-    for item in items {
-        writeln!(file, "\n\n# {}\n{}", item.name, item.content)?;
-        println!("  + Appended to .github/copilot-instructions.md");
+    if !select.tags.is_empty() {
+        let before = items.len();
+        items = filter_items_by_tags(conn, items, &select.tags, select.tag_mode)?;
+        if before > 0 && items.is_empty() {
+            let tags: Vec<String> = select.tags.iter().map(|t| format!("--tag {}", t)).collect();
+            unmatched.push(tags.join(" "));
+        }
     }
-    Ok(())
+
+    Ok((items, unmatched))
 }
 
-/// Run CLI commands. Returns true if a command was executed, false if GUI should launch.
-pub fn run() -> bool {
-    let cli = Cli::parse();
+fn resolve_agent_selectors(conn: &Connection, select: &AgentSelectors) -> SqliteResult<(Vec<Agent>, Vec<String>)> {
+    if select.all {
+        return Ok((get_all_agents(conn)?, vec![]));
+    }
 
-    // If no subcommand, return false to launch GUI
-    let command = match cli.command {
-        Some(cmd) => cmd,
-        None => return false,
-    };
+    let mut unmatched: Vec<String> = Vec::new();
 
-    let db_path = get_db_path();
+    let by_id = get_agents_by_ids(conn, &select.ids)?;
+    for id in &select.ids {
+        if !by_id.iter().any(|agent| &agent.id == id) {
+            unmatched.push(format!("--ids {}", id));
+        }
+    }
 
-    if !db_path.exists() {
-        eprintln!("Error: Promption database not found at {:?}", db_path);
-        eprintln!("Make sure you have run the Promption app at least once.");
-        std::process::exit(1);
+    let by_name = get_agents_by_names(conn, &select.names)?;
+    for name in &select.names {
+        if !by_name.iter().any(|agent| &agent.name == name) {
+            unmatched.push(format!("--names {}", name));
+        }
     }
 
-    let conn = match Connection::open(&db_path) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Error: Could not open database: {}", e);
-            std::process::exit(1);
+    let mut found = vec![by_id, by_name];
+    for pattern in &select.patterns {
+        let matched = get_agents_matching(conn, pattern)?;
+        if matched.is_empty() {
+            unmatched.push(format!("--match '{}'", pattern));
         }
-    };
+        found.push(matched);
+    }
 
-    match command {
-        Commands::Sync { ids, target } => {
-            if ids.is_empty() {
-                eprintln!("Error: No item IDs provided. Use --ids=id1,id2,id3");
-                std::process::exit(1);
-            }
+    let mut seen: HashSet<String> = HashSet::new();
+    let agents: Vec<Agent> = found
+        .into_iter()
+        .flatten()
+        .filter(|agent| seen.insert(agent.id.clone()))
+        .collect();
 
-            println!(
-                "Syncing {} item(s) to {:?} configuration...",
-                ids.len(),
-                target
-            );
+    Ok((agents, unmatched))
+}
 
-            match get_items_by_ids(&conn, &ids) {
-                Ok(items) => {
-                    if items.is_empty() {
-                        eprintln!("Warning: No items found with the provided IDs");
-                        std::process::exit(1);
-                    }
+fn warn_unmatched_selectors(unmatched: &[String]) {
+    if unmatched.is_empty() {
+        return;
+    }
+    eprintln!("Warning: {} selector(s) matched nothing:", unmatched.len());
+    for selector in unmatched {
+        eprintln!("  {}", selector);
+    }
+}
 
-                    if items.len() != ids.len() {
-                        eprintln!(
-                            "Warning: Found {} items, expected {} (some IDs may be invalid)",
-                            items.len(),
-                            ids.len()
-                        );
-                    }
+/// What applying a planned write would do to the file on disk
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum WriteAction {
+    Create,
+    Overwrite,
+    Unchanged,
+}
 
-                    match sync_items(&items, target) {
-                        Ok(()) => {
-                            println!("\nDone! {} item(s) synced.", items.len());
-                        }
-                        Err(e) => {
-                            eprintln!("Error writing files: {}", e);
-                            std::process::exit(1);
-                        }
+#[derive(Debug)]
+struct PlannedWrite {
+    path: PathBuf,
+    content: String,
+    sources: Vec<LockSource>,
+    /// Config file that collects entries across syncs, such as opencode.json
+    shared: bool,
+}
+
+impl PlannedWrite {
+    /// Only files generated from library entries are recorded in the lockfile
+    /// (and so eligible for prune/clean); user-owned files are left out.
+    fn is_recorded(&self) -> bool {
+        !self.sources.is_empty()
+    }
+}
+
+/// Files a sync intends to write. Targets only describe their output here;
+/// the plan then either previews it (dry run / diff) or writes it.
+#[derive(Debug, Default)]
+struct SyncPlan {
+    writes: Vec<PlannedWrite>,
+    deletes: Vec<PathBuf>,
+}
+
+impl SyncPlan {
+    /// Queue a file write generated from `sources`. A later write to the same
+    /// path replaces the earlier content and adds its sources.
+    fn write(&mut self, path: impl Into<PathBuf>, content: impl Into<String>, sources: Vec<LockSource>) {
+        let path = path.into();
+        let content = content.into();
+        match self.writes.iter_mut().find(|w| w.path == path) {
+            Some(existing) => {
+                existing.content = content;
+                for source in sources {
+                    if !existing.sources.contains(&source) {
+                        existing.sources.push(source);
                     }
                 }
-                Err(e) => {
-                    eprintln!("Database error: {}", e);
-                    std::process::exit(1);
+            }
+            None => self.writes.push(PlannedWrite {
+                path,
+                content,
+                sources,
+                shared: false,
+            }),
+        }
+    }
+
+    /// Queue a write to a user-owned config file that collects entries across
+    /// syncs, such as opencode.json. Its lock entry keeps the sources of earlier
+    /// syncs, and prune or clean never delete it.
+    fn write_shared(&mut self, path: impl Into<PathBuf>, content: impl Into<String>, sources: Vec<LockSource>) {
+        let path = path.into();
+        self.write(path.clone(), content, sources);
+        if let Some(w) = self.writes.iter_mut().find(|w| w.path == path) {
+            w.shared = true;
+        }
+    }
+
+    /// Queue an edit to a file that holds user content, such as a shared document
+    /// stripped of its managed blocks. It must never be deleted by prune or clean
+    /// and is therefore left out of the lockfile.
+    fn write_untracked(&mut self, path: impl Into<PathBuf>, content: impl Into<String>) {
+        self.write(path, content, vec![]);
+    }
+
+    fn write_item(&mut self, item: &Item, path: impl Into<PathBuf>, content: impl Into<String>) {
+        self.write(path, content, vec![LockSource::item(item)]);
+    }
+
+    /// Content a path will have once the plan is applied: the planned content if
+    /// queued, otherwise whatever is on disk.
+    fn current_content(&self, path: &Path) -> std::io::Result<Option<String>> {
+        if let Some(w) = self.writes.iter().find(|w| w.path == path) {
+            return Ok(Some(w.content.clone()));
+        }
+        read_existing(path)
+    }
+
+    fn apply(&self) -> std::io::Result<()> {
+        for w in &self.writes {
+            let action = planned_action(w)?;
+            if action != WriteAction::Unchanged {
+                if let Some(parent) = w.path.parent() {
+                    fs::create_dir_all(parent)?;
                 }
+                fs::write(&w.path, &w.content)?;
             }
+            let marker = match action {
+                WriteAction::Create => '+',
+                WriteAction::Overwrite => '~',
+                WriteAction::Unchanged => '=',
+            };
+            println!("  {} {}", marker, w.path.display());
         }
-        Commands::List { r#type } => {
-            let type_filter = r#type.as_deref();
 
-            match get_all_items(&conn, type_filter) {
-                Ok(items) => {
-                    if items.is_empty() {
-                        println!("No items found.");
-                        return true;
-                    }
+        for path in &self.deletes {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            remove_empty_parents(path);
+            println!("  - {}", path.display());
+        }
+        Ok(())
+    }
 
-                    println!("{:<36}  {:<10}  {}", "ID", "TYPE", "NAME");
-                    println!("{}", "-".repeat(70));
+    fn preview(&self, show_diff: bool) -> std::io::Result<()> {
+        let (mut created, mut overwritten, mut unchanged) = (0, 0, 0);
 
-                    for item in items {
-                        println!("{:<36}  {:<10}  {}", item.id, item.item_type, item.name);
-                    }
+        for w in &self.writes {
+            let action = planned_action(w)?;
+            let label = match action {
+                WriteAction::Create => {
+                    created += 1;
+                    "create"
                 }
-                Err(e) => {
-                    eprintln!("Database error: {}", e);
-                    std::process::exit(1);
+                WriteAction::Overwrite => {
+                    overwritten += 1;
+                    "overwrite"
+                }
+                WriteAction::Unchanged => {
+                    unchanged += 1;
+                    "unchanged"
                 }
+            };
+            println!("  {:<9}  {}", label, w.path.display());
+
+            if show_diff && action != WriteAction::Unchanged {
+                let old = read_existing(&w.path)?.unwrap_or_default();
+                print!("{}", unified_diff(&w.path, &old, &w.content, action == WriteAction::Create));
             }
         }
-        Commands::SyncAgents { ids } => {
-            if ids.is_empty() {
-                eprintln!("Error: No agent IDs provided. Use --ids=id1,id2,id3");
-                std::process::exit(1);
-            }
 
-            println!("Syncing {} agent(s) to opencode.json...", ids.len());
+        for path in &self.deletes {
+            println!("  {:<9}  {}", "delete", path.display());
+        }
 
-            match get_agents_by_ids(&conn, &ids) {
-                Ok(agents) => {
-                    if agents.is_empty() {
-                        eprintln!("Warning: No agents found with the provided IDs");
-                        std::process::exit(1);
-                    }
+        println!(
+            "\nDry run: {} to create, {} to overwrite, {} unchanged, {} to delete. No files were written.",
+            created,
+            overwritten,
+            unchanged,
+            self.deletes.len()
+        );
+        Ok(())
+    }
 
-                    if agents.len() != ids.len() {
-                        eprintln!(
-                            "Warning: Found {} agents, expected {} (some IDs may be invalid)",
-                            agents.len(),
-                            ids.len()
-                        );
-                    }
+    /// Preview the plan when `dry_run` or `show_diff` is set, otherwise write it
+    /// and record the written files in the project lockfile under `target`.
+    fn run(&self, target: &str, dry_run: bool, show_diff: bool) -> std::io::Result<()> {
+        if dry_run || show_diff {
+            return self.preview(show_diff);
+        }
 
-                    match sync_agents_to_opencode(&agents) {
-                        Ok(()) => {
-                            println!("\nDone! {} agent(s) synced to opencode.json.", agents.len());
-                        }
-                        Err(e) => {
-                            eprintln!("Error writing opencode.json: {}", e);
-                            std::process::exit(1);
-                        }
+        self.apply()?;
+
+        let mut lock = SyncLock::load()?;
+        let released = self
+            .deletes
+            .iter()
+            .chain(self.writes.iter().filter(|w| !w.is_recorded()).map(|w| &w.path))
+            .map(|path| lock_path_string(path))
+            .collect::<HashSet<_>>();
+        lock.entries.retain(|e| !released.contains(&e.path));
+        let recorded: Vec<&PlannedWrite> = self.writes.iter().filter(|w| w.is_recorded()).collect();
+        for w in &recorded {
+            lock.record(LockEntry {
+                path: lock_path_string(&w.path),
+                target: target.to_string(),
+                hash: lock_hash(&w.content),
+                sources: w.sources.clone(),
+                shared: w.shared,
+            });
+        }
+        lock.save()?;
+        if !recorded.is_empty() {
+            println!("  = Recorded {} file(s) in {}", recorded.len(), LOCK_FILE);
+        }
+        Ok(())
+    }
+}
+
+const LOCK_FILE: &str = ".promption.lock";
+const LOCK_VERSION: u32 = 1;
+
+/// An item or agent that contributed to a synced file, with a hash of its
+/// library state at sync time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LockSource {
+    kind: String,
+    id: String,
+    name: String,
+    hash: String,
+}
+
+impl LockSource {
+    fn item(item: &Item) -> Self {
+        LockSource {
+            kind: "item".to_string(),
+            id: item.id.clone(),
+            name: item.name.clone(),
+            hash: item_hash(item),
+        }
+    }
+
+    fn agent(agent: &Agent) -> Self {
+        LockSource {
+            kind: "agent".to_string(),
+            id: agent.id.clone(),
+            name: agent.name.clone(),
+            hash: agent_hash(agent),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockEntry {
+    path: String,
+    target: String,
+    hash: String,
+    sources: Vec<LockSource>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    shared: bool,
+}
+
+/// `.promption.lock`: every file promption wrote into the project
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncLock {
+    version: u32,
+    entries: Vec<LockEntry>,
+}
+
+impl SyncLock {
+    fn load() -> std::io::Result<Self> {
+        match read_existing(Path::new(LOCK_FILE))? {
+            Some(content) => serde_json::from_str(&content).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Could not parse {}: {}", LOCK_FILE, e),
+                )
+            }),
+            None => Ok(SyncLock {
+                version: LOCK_VERSION,
+                entries: vec![],
+            }),
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(LOCK_FILE, json + "\n")
+    }
+
+    /// Add an entry, replacing any previous record of the same path. Shared
+    /// files also keep the sources recorded by earlier syncs.
+    fn record(&mut self, entry: LockEntry) {
+        match self.entries.iter_mut().find(|e| e.path == entry.path) {
+            Some(existing) if entry.shared => {
+                let previous = std::mem::replace(existing, entry);
+                for source in previous.sources {
+                    if !existing.sources.iter().any(|s| s.kind == source.kind && s.id == source.id) {
+                        existing.sources.push(source);
                     }
                 }
-                Err(e) => {
-                    eprintln!("Database error: {}", e);
-                    std::process::exit(1);
-                }
             }
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
         }
-        Commands::ListAgents => {
-            match get_all_agents(&conn) {
-                Ok(agents) => {
-                    if agents.is_empty() {
-                        println!("No agents found.");
-                        return true;
-                    }
+    }
+}
 
-                    println!("{:<36}  {:<10}  {}", "ID", "MODE", "NAME");
-                    println!("{}", "-".repeat(70));
+fn lock_path_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
 
-                    for agent in agents {
-                        println!("{:<36}  {:<10}  {}", agent.id, agent.mode, agent.name);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Database error: {}", e);
-                    std::process::exit(1);
+fn content_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// Hash recorded for a synced file. Shared documents only hash their managed
+/// blocks, so text the user writes around them is not drift.
+fn lock_hash(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let blocks: Vec<String> = parse_managed_document(&lines)
+        .into_iter()
+        .filter_map(|part| match part {
+            DocumentPart::Block { id, body } => Some(format!("{}\n{}", id, body.join("\n"))),
+            DocumentPart::Text(_) => None,
+        })
+        .collect();
+    if blocks.is_empty() {
+        content_hash(content)
+    } else {
+        content_hash(&blocks.join("\n"))
+    }
+}
+
+fn item_hash(item: &Item) -> String {
+    let mut fields = format!("{}\0{}\0{}", item.item_type, item.name, item.content);
+    // Only hashed when present so locks written before metadata existed stay valid
+    if !item.metadata.is_empty() {
+        fields.push('\0');
+        fields.push_str(&serde_json::to_string(&item.metadata).unwrap_or_default());
+    }
+    content_hash(&fields)
+}
+
+fn agent_hash(agent: &Agent) -> String {
+    content_hash(&format!(
+        "{}\0{}\0{}\0{}\0{}\0{}",
+        agent.name,
+        agent.mode,
+        agent.model.as_deref().unwrap_or_default(),
+        agent.prompt_content.as_deref().unwrap_or_default(),
+        agent.tools_config.as_deref().unwrap_or_default(),
+        agent.permissions_config.as_deref().unwrap_or_default(),
+    ))
+}
+
+/// Queue deletion of every file recorded for `target` in the lockfile that the
+/// plan no longer writes. Files edited by hand since the sync are kept, shared
+/// documents only lose their managed blocks, and shared config files such as
+/// opencode.json are kept but no longer tracked.
+fn plan_prune(plan: &mut SyncPlan, lock: &SyncLock, target: &str) -> std::io::Result<()> {
+    for entry in lock.entries.iter().filter(|e| e.target == target) {
+        let path = PathBuf::from(&entry.path);
+        if plan.writes.iter().any(|w| w.path == path) {
+            continue;
+        }
+
+        if entry.shared {
+            // They also hold the user's own settings, so promption's entries stay too
+            eprintln!("Warning: Keeping {} (shared config; remove promption's entries by hand)", entry.path);
+            match read_existing(&path)? {
+                Some(content) => plan.write_untracked(path, content),
+                None => plan.deletes.push(path),
+            }
+            continue;
+        }
+
+        if let Some(content) = read_existing(&path)? {
+            if lock_hash(&content) != entry.hash {
+                eprintln!("Warning: Keeping {} (edited since it was synced)", entry.path);
+                continue;
+            }
+
+            if content.lines().any(|line| managed_block_id(line).is_some()) {
+                let remainder = update_managed_blocks(&content, &[]);
+                if !remainder.trim().is_empty() {
+                    plan.write_untracked(path, remainder);
+                    continue;
                 }
             }
         }
-        Commands::CreateAgent {
-            name,
-            mode,
-            model,
-            prompt,
-            prompt_file,
-            tools,
-            permissions,
-            format,
-        } => {
-            println!("Creating agent '{}'...", name);
+        plan.deletes.push(path);
+    }
+    Ok(())
+}
 
-            match create_agent_cli(
-                &conn,
-                &name,
-                mode,
-                model.as_deref(),
-                prompt.as_deref(),
-                prompt_file,
-                &tools,
-                &permissions,
-            ) {
-                Ok(agent) => {
-                    match format {
-                        OutputFormat::Json => {
-                            print_agent_json(&agent);
-                        }
-                        OutputFormat::Text => {
-                            println!("\n✓ Agent created successfully!");
-                            println!("  ID: {}", agent.id);
-                            println!("  Name: {}", agent.name);
-                            println!("  Mode: {}", agent.mode);
-                            if let Some(m) = &agent.model {
-                                println!("  Model: {}", m);
+/// Targets recorded in the lockfile that are not in `listed`, in the order they
+/// were first synced. Entries synced with `--mode` belong to their base target.
+fn dropped_lock_targets(lock: &SyncLock, listed: &[String]) -> Vec<String> {
+    let mut dropped: Vec<String> = Vec::new();
+    for entry in &lock.entries {
+        let base = entry.target.split(':').next().unwrap_or_default();
+        if !listed.iter().any(|t| t == base) && !dropped.contains(&entry.target) {
+            dropped.push(entry.target.clone());
+        }
+    }
+    dropped
+}
+
+/// Remove directories left empty by a deletion, stopping at the project root
+fn remove_empty_parents(path: &Path) {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir.as_os_str().is_empty() || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+fn read_existing(path: &Path) -> std::io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn planned_action(write: &PlannedWrite) -> std::io::Result<WriteAction> {
+    Ok(match read_existing(&write.path)? {
+        None => WriteAction::Create,
+        Some(existing) if existing == write.content => WriteAction::Unchanged,
+        Some(_) => WriteAction::Overwrite,
+    })
+}
+
+fn unified_diff(path: &Path, old: &str, new: &str, is_new_file: bool) -> String {
+    let old_header = if is_new_file {
+        "/dev/null".to_string()
+    } else {
+        format!("a/{}", path.display())
+    };
+    let new_header = format!("b/{}", path.display());
+
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&old_header, &new_header)
+        .missing_newline_hint(false)
+        .to_string()
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum FileState {
+    Ok,
+    Modified,
+    Deleted,
+}
+
+impl FileState {
+    fn as_str(&self) -> &str {
+        match self {
+            FileState::Ok => "ok",
+            FileState::Modified => "modified",
+            FileState::Deleted => "deleted",
+        }
+    }
+}
+
+/// Drift for one lockfile entry: the file on disk, plus sources that changed
+/// or disappeared in the library since the sync
+#[derive(Debug)]
+struct EntryStatus {
+    file: FileState,
+    outdated: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl EntryStatus {
+    fn is_clean(&self) -> bool {
+        self.file == FileState::Ok && self.outdated.is_empty() && self.removed.is_empty()
+    }
+
+    fn label(&self) -> &str {
+        if self.file != FileState::Ok {
+            self.file.as_str()
+        } else if !self.outdated.is_empty() {
+            "outdated"
+        } else if !self.removed.is_empty() {
+            "orphaned"
+        } else {
+            "ok"
+        }
+    }
+}
+
+fn file_state(entry: &LockEntry) -> FileState {
+    match read_existing(Path::new(&entry.path)) {
+        Ok(Some(content)) if lock_hash(&content) == entry.hash => FileState::Ok,
+        Ok(Some(_)) => FileState::Modified,
+        Ok(None) => FileState::Deleted,
+        Err(_) => FileState::Modified,
+    }
+}
+
+fn check_lock_entry(conn: &Connection, entry: &LockEntry) -> SqliteResult<EntryStatus> {
+    let file = file_state(entry);
+
+    let mut outdated = Vec::new();
+    let mut removed = Vec::new();
+    for source in &entry.sources {
+        let ids = [source.id.clone()];
+        let current = match source.kind.as_str() {
+            "agent" => get_agents_by_ids(conn, &ids)?.first().map(agent_hash),
+            _ => get_items_by_ids(conn, &ids)?.first().map(item_hash),
+        };
+        match current {
+            Some(hash) if hash != source.hash => outdated.push(source.name.clone()),
+            Some(_) => {}
+            None => removed.push(source.name.clone()),
+        }
+    }
+
+    Ok(EntryStatus {
+        file,
+        outdated,
+        removed,
+    })
+}
+
+fn sync_claude(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let skills_path = PathBuf::from(".claude/skills");
+    let memory_path = PathBuf::from("CLAUDE.md");
+
+    let mut memory_items: Vec<&Item> = Vec::new();
+
+    for item in items {
+        let slug = slugify(&item.name);
+        match item.item_type.as_str() {
+            "skill" => {
+                let meta = item.metadata.for_target(ToolTarget::Claude);
+                plan.write_item(item, skills_path.join(&slug).join("SKILL.md"), skill_document(item, &slug, &meta));
+            }
+            // Rules and workflows become managed sections of CLAUDE.md
+            _ => memory_items.push(item),
+        }
+    }
+
+    let blocks = memory_items.iter().map(|item| section_block(item)).collect();
+    write_managed_document(plan, memory_path, blocks, &memory_items)
+}
+
+fn sync_agents_md(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let agents_md_path = PathBuf::from("AGENTS.md");
+
+    let sections = without_skills(items, "AGENTS.md only holds rules and workflows");
+
+    let mut blocks: Vec<ManagedBlock> = Vec::new();
+    if !sections.is_empty() {
+        let toc: Vec<String> = sections
+            .iter()
+            .map(|item| format!("- [{}](#{})", item.name, slugify(&item.name)))
+            .collect();
+        blocks.push(ManagedBlock {
+            id: "toc".to_string(),
+            body: format!("## Contents\n\n{}", toc.join("\n")),
+        });
+    }
+    blocks.extend(sections.iter().map(|item| section_block(item)));
+
+    write_managed_document(plan, agents_md_path, blocks, &sections)
+}
+
+/// Gemini CLI custom command file (`.gemini/commands/<name>.toml`)
+#[derive(Serialize)]
+struct GeminiCommand<'a> {
+    description: &'a str,
+    prompt: &'a str,
+}
+
+fn sync_gemini(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let commands_path = PathBuf::from(".gemini/commands");
+    let context_path = PathBuf::from("GEMINI.md");
+
+    let mut context_items: Vec<&Item> = Vec::new();
+
+    for item in items {
+        let slug = slugify(&item.name);
+        match item.item_type.as_str() {
+            "workflow" => {
+                // Workflows become /<slug> custom commands
+                let meta = item.metadata.for_target(ToolTarget::Gemini);
+                let command = GeminiCommand {
+                    description: meta.description_or(&item.name),
+                    prompt: &item.content,
+                };
+                let content = toml::to_string_pretty(&command).map_err(std::io::Error::other)?;
+                plan.write_item(item, commands_path.join(format!("{}.toml", slug)), content);
+            }
+            _ => context_items.push(item),
+        }
+    }
+
+    let blocks = context_items.iter().map(|item| section_block(item)).collect();
+    write_managed_document(plan, context_path, blocks, &context_items)
+}
+
+fn sync_continue(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let rules_path = PathBuf::from(".continue/rules");
+    let prompts_path = PathBuf::from(".continue/prompts");
+
+    for item in items {
+        let slug = slugify(&item.name);
+        match item.item_type.as_str() {
+            "rule" => {
+                let meta = item.metadata.for_target(ToolTarget::Continue);
+                let activation = meta.effective_activation(&item.name, Activation::Always);
+                let mut frontmatter = format!("---\nname: {}\n", yaml_scalar(&item.name));
+                if activation == Activation::Glob {
+                    let globs: Vec<String> = meta.globs.iter().map(|g| yaml_scalar(g)).collect();
+                    frontmatter.push_str(&format!("globs: [{}]\n", globs.join(", ")));
+                }
+                if let Some(description) = &meta.description {
+                    frontmatter.push_str(&format!("description: {}\n", yaml_scalar(description)));
+                }
+                let content = format!(
+                    "{}alwaysApply: {}\n---\n\n{}",
+                    frontmatter,
+                    activation == Activation::Always,
+                    item.content
+                );
+                plan.write_item(item, rules_path.join(format!("{}.md", slug)), content);
+            }
+            _ => {
+                // Skills and workflows become slash-command prompt files
+                let meta = item.metadata.for_target(ToolTarget::Continue);
+                let content = format!(
+                    "name: {}\ndescription: {}\n---\n{}",
+                    slug,
+                    yaml_scalar(meta.description_or(&item.name)),
+                    item.content
+                );
+                plan.write_item(item, prompts_path.join(format!("{}.prompt", slug)), content);
+            }
+        }
+    }
+    Ok(())
+}
+
+const AIDER_CONVENTIONS_FILE: &str = "CONVENTIONS.md";
+const AIDER_CONFIG_FILE: &str = ".aider.conf.yml";
+
+fn sync_aider(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let conventions_path = PathBuf::from(AIDER_CONVENTIONS_FILE);
+    let config_path = PathBuf::from(AIDER_CONFIG_FILE);
+
+    let sections = without_skills(items, "Aider only reads conventions");
+    if sections.is_empty() && plan.current_content(&conventions_path)?.is_none() {
+        return Ok(());
+    }
+
+    let blocks = sections.iter().map(|item| section_block(item)).collect();
+    write_managed_document(plan, conventions_path, blocks, &sections)?;
+
+    // The config belongs to the user: only make sure Aider loads the conventions
+    let config = plan.current_content(&config_path)?.unwrap_or_default();
+    let updated = aider_config_with_read(&config, AIDER_CONVENTIONS_FILE);
+    if updated != config {
+        plan.write_shared(config_path, updated, sections.into_iter().map(LockSource::item).collect());
+    }
+    Ok(())
+}
+
+/// Add `file` to the top-level `read:` key of an `.aider.conf.yml`, keeping
+/// every other line (keys, comments, formatting) as it is. Handles block
+/// lists, inline `[...]` lists and a single scalar value.
+fn aider_config_with_read(config: &str, file: &str) -> String {
+    let unquote = |value: &str| value.trim().trim_matches(['"', '\'']).to_string();
+    let newline = if config.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = config.lines().map(str::to_string).collect();
+
+    let Some(key_line) = lines.iter().position(|line| line.starts_with("read:")) else {
+        let mut out = config.to_string();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push_str(newline);
+        }
+        out.push_str(&format!("read:{}  - {}{}", newline, file, newline));
+        return out;
+    };
+
+    let value = lines[key_line]["read:".len()..].to_string();
+    let value = value.split(" #").next().unwrap_or_default().trim().to_string();
+
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        let mut entries: Vec<String> = inner
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string)
+            .collect();
+        if entries.iter().any(|e| unquote(e) == file) {
+            return config.to_string();
+        }
+        entries.push(file.to_string());
+        lines[key_line] = format!("read: [{}]", entries.join(", "));
+    } else if !value.is_empty() {
+        if unquote(&value) == file {
+            return config.to_string();
+        }
+        lines[key_line] = "read:".to_string();
+        lines.insert(key_line + 1, format!("  - {}", value));
+        lines.insert(key_line + 2, format!("  - {}", file));
+    } else {
+        // Block list: entries follow on indented or "- " lines
+        let mut last_entry = key_line;
+        let mut indent = "  ".to_string();
+        for (i, line) in lines.iter().enumerate().skip(key_line + 1) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if !(line.starts_with(' ') || line.starts_with('-')) {
+                break;
+            }
+            if let Some(entry) = trimmed.strip_prefix('-') {
+                if unquote(entry.split(" #").next().unwrap_or_default()) == file {
+                    return config.to_string();
+                }
+                indent = line[..line.len() - trimmed.len()].to_string();
+            }
+            last_entry = i;
+        }
+        lines.insert(last_entry + 1, format!("{}- {}", indent, file));
+    }
+
+    let mut out = lines.join(newline);
+    out.push_str(newline);
+    out
+}
+
+fn sync_zed(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    // Zed keeps its prompt library in its own database, so there is nowhere to put skills
+    let template = TemplateTarget {
+        name: target_name(ToolTarget::Zed),
+        mode: TemplateMode::Combined,
+        path: Some(".rules".to_string()),
+        types: vec![ItemTypeArg::Rule, ItemTypeArg::Workflow],
+        ..Default::default()
+    };
+    sync_template(items, &template, plan)
+}
+
+/// JetBrains Junie reads one guidelines document; AI Assistant reads one file per rule
+fn sync_jetbrains(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let guidelines_path = PathBuf::from(".junie/guidelines.md");
+    let rules_path = PathBuf::from(".aiassistant/rules");
+
+    let sections = without_skills(items, "JetBrains only reads guidelines and rules");
+
+    for item in sections.iter().filter(|item| item.item_type == "rule") {
+        plan.write_item(item, rules_path.join(format!("{}.md", slugify(&item.name))), &item.content);
+    }
+
+    let blocks = sections.iter().map(|item| section_block(item)).collect();
+    write_managed_document(plan, guidelines_path, blocks, &sections)
+}
+
+/// Kiro steering files. Inclusion follows the item's activation metadata; rules
+/// default to always, while skills and workflows are pulled in with `#name`.
+fn sync_kiro(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let steering_path = PathBuf::from(".kiro/steering");
+
+    for item in items {
+        let meta = item.metadata.for_target(ToolTarget::Kiro);
+        let default = if item.item_type == "rule" { Activation::Always } else { Activation::Manual };
+
+        // Kiro has no model-decided inclusion, so those items are pulled in by hand
+        let frontmatter = match meta.effective_activation(&item.name, default) {
+            Activation::Always => "---\ninclusion: always\n---\n\n".to_string(),
+            Activation::Model | Activation::Manual => "---\ninclusion: manual\n---\n\n".to_string(),
+            Activation::Glob => {
+                let patterns: Vec<String> = meta.globs.iter().map(|g| yaml_scalar(g)).collect();
+                let pattern = match patterns.as_slice() {
+                    [single] => single.clone(),
+                    many => format!("[{}]", many.join(", ")),
+                };
+                format!("---\ninclusion: fileMatch\nfileMatchPattern: {}\n---\n\n", pattern)
+            }
+        };
+
+        plan.write_item(
+            item,
+            steering_path.join(format!("{}.md", slugify(&item.name))),
+            frontmatter + &item.content,
+        );
+    }
+    Ok(())
+}
+
+/// Roo Code (`.roo/`) and Kilo Code (`.kilocode/`) share one layout: rules in
+/// `rules/` (or `rules-<mode>/` for a single mode) plus slash commands.
+fn sync_roo(items: &[Item], target: ToolTarget, mode: Option<&str>, plan: &mut SyncPlan) -> std::io::Result<()> {
+    let (root, commands_dir) = match target {
+        ToolTarget::Kilo => (Path::new(".kilocode"), "workflows"),
+        _ => (Path::new(".roo"), "commands"),
+    };
+
+    if let Some(mode) = mode {
+        // Everything becomes instructions that are loaded only in that mode
+        let mode_path = root.join(format!("rules-{}", mode));
+        for item in items {
+            plan.write_item(item, mode_path.join(format!("{}.md", slugify(&item.name))), &item.content);
+        }
+        return Ok(());
+    }
+
+    let rules_path = root.join("rules");
+    let commands_path = root.join(commands_dir);
+
+    for item in items {
+        let slug = slugify(&item.name);
+        match item.item_type.as_str() {
+            "rule" => {
+                plan.write_item(item, rules_path.join(format!("{}.md", slug)), &item.content);
+            }
+            _ => {
+                let meta = item.metadata.for_target(target);
+                let content = format!(
+                    "---\ndescription: {}\n---\n\n{}",
+                    yaml_scalar(meta.description_or(&item.name)),
+                    item.content
+                );
+                plan.write_item(item, commands_path.join(format!("{}.md", slug)), content);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `SKILL.md` with the `name`/`description` frontmatter agents use to pick skills
+fn skill_document(item: &Item, slug: &str, meta: &ItemMetadata) -> String {
+    format!(
+        "---\nname: {}\ndescription: {}\n---\n\n{}",
+        slug,
+        yaml_scalar(meta.description_or(&item.name)),
+        item.content
+    )
+}
+
+/// Windsurf-style rule frontmatter (`trigger`, `globs`, `description`), which
+/// Antigravity reads as well
+fn trigger_frontmatter(item: &Item, meta: &ItemMetadata, default: Activation) -> String {
+    let mut frontmatter = String::from("---\n");
+    match meta.effective_activation(&item.name, default) {
+        Activation::Always => frontmatter.push_str("trigger: always_on\n"),
+        Activation::Glob => frontmatter.push_str(&format!("trigger: glob\nglobs: {}\n", meta.globs.join(","))),
+        Activation::Model => frontmatter.push_str("trigger: model_decision\n"),
+        Activation::Manual => frontmatter.push_str("trigger: manual\n"),
+    }
+    frontmatter.push_str(&format!("description: {}\n---\n\n", yaml_scalar(meta.description_or(&item.name))));
+    frontmatter
+}
+
+/// Quote a frontmatter value when plain YAML would misread it
+fn yaml_scalar(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.trim() != value
+        || value.contains([':', '#', '\n', '"', '\''])
+        || value.starts_with(['-', '[', '{', '*', '&', '!', '|', '>', '%', '@', '`']);
+    if needs_quotes {
+        serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A section of a shared document owned by promption, delimited by
+/// `<!-- promption:begin id=... -->` / `<!-- promption:end -->` markers
+#[derive(Debug)]
+struct ManagedBlock {
+    id: String,
+    body: String,
+}
+
+const BLOCK_BEGIN_PREFIX: &str = "<!-- promption:begin id=";
+const BLOCK_END: &str = "<!-- promption:end -->";
+
+fn managed_block_id(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix(BLOCK_BEGIN_PREFIX)
+        .and_then(|rest| rest.strip_suffix("-->"))
+        .map(str::trim)
+}
+
+fn render_managed_block(block: &ManagedBlock) -> String {
+    format!(
+        "{}{} -->\n{}\n{}",
+        BLOCK_BEGIN_PREFIX,
+        block.id,
+        block.body.trim_end(),
+        BLOCK_END
+    )
+}
+
+/// A line of a shared document outside any managed block, or a whole block
+#[derive(Debug, PartialEq)]
+enum DocumentPart<'a> {
+    Text(&'a str),
+    /// A managed block's id and the lines between its markers
+    Block { id: &'a str, body: &'a [&'a str] },
+}
+
+/// Split a shared document into text lines and managed blocks. A begin marker
+/// whose end marker does not come before the next begin marker is plain text.
+fn parse_managed_document<'a>(lines: &'a [&'a str]) -> Vec<DocumentPart<'a>> {
+    let mut parts = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let id = managed_block_id(lines[i]);
+        let end = id.and_then(|_| {
+            lines[i + 1..]
+                .iter()
+                .position(|l| l.trim() == BLOCK_END || managed_block_id(l).is_some())
+                .filter(|&offset| lines[i + 1 + offset].trim() == BLOCK_END)
+                .map(|offset| i + 1 + offset)
+        });
+
+        match (id, end) {
+            (Some(id), Some(end)) => {
+                parts.push(DocumentPart::Block {
+                    id,
+                    body: &lines[i + 1..end],
+                });
+                i = end + 1;
+            }
+            _ => {
+                parts.push(DocumentPart::Text(lines[i]));
+                i += 1;
+            }
+        }
+    }
+    parts
+}
+
+/// Update the managed blocks in `existing`: blocks listed in `blocks` are
+/// replaced in place, blocks no longer listed are removed, new ones are appended.
+/// Text outside the markers is never touched.
+fn update_managed_blocks(existing: &str, blocks: &[ManagedBlock]) -> String {
+    let lines: Vec<&str> = existing.lines().collect();
+    let parts = parse_managed_document(&lines);
+    let mut out: Vec<String> = Vec::new();
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut skip_blank = false;
+
+    for part in parts {
+        match part {
+            DocumentPart::Block { id, .. } => match blocks.iter().find(|b| b.id == id) {
+                Some(block) if !placed.contains(block.id.as_str()) => {
+                    out.push(render_managed_block(block));
+                    placed.insert(block.id.as_str());
+                }
+                // Dropped block: also swallow the blank line separating it from the next one
+                _ => {
+                    skip_blank = true;
+                    continue;
+                }
+            },
+            DocumentPart::Text(line) if skip_blank && line.trim().is_empty() => {}
+            DocumentPart::Text(line) => out.push(line.to_string()),
+        }
+        skip_blank = false;
+    }
+
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+
+    for block in blocks.iter().filter(|b| !placed.contains(b.id.as_str())) {
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.push(render_managed_block(block));
+    }
+
+    if out.is_empty() {
+        String::new()
+    } else {
+        out.join("\n") + "\n"
+    }
+}
+
+/// `## Name` section for an item in a shared document
+fn section_block(item: &Item) -> ManagedBlock {
+    ManagedBlock {
+        id: item.id.clone(),
+        body: format!("## {}\n\n{}", item.name, item.content.trim_end()),
+    }
+}
+
+/// Items other than skills, warning about each skill the target has no place for
+fn without_skills<'a>(items: &'a [Item], reason: &str) -> Vec<&'a Item> {
+    let (skills, rest): (Vec<&Item>, Vec<&Item>) = items.iter().partition(|item| item.item_type == "skill");
+    for skill in skills {
+        eprintln!("Warning: Skipping skill '{}' ({})", skill.name, reason);
+    }
+    rest
+}
+
+/// Queue the managed sections of a shared document such as CLAUDE.md, generated
+/// from `items`. A document holding no promption sections is left untouched when
+/// there is nothing to add, and CRLF documents keep their line endings.
+fn write_managed_document(
+    plan: &mut SyncPlan,
+    path: impl Into<PathBuf>,
+    blocks: Vec<ManagedBlock>,
+    items: &[&Item],
+) -> std::io::Result<()> {
+    let path = path.into();
+    let existing = plan.current_content(&path)?.unwrap_or_default();
+    if blocks.is_empty() && !existing.lines().any(|line| managed_block_id(line).is_some()) {
+        return Ok(());
+    }
+
+    let mut updated = update_managed_blocks(&existing, &blocks);
+    if existing.contains("\r\n") {
+        updated = updated.replace('\n', "\r\n");
+    }
+    plan.write(path, updated, items.iter().map(|item| LockSource::item(item)).collect());
+    Ok(())
+}
+
+/// Where `sync` writes: a built-in tool or a template target from targets.toml
+#[derive(Debug, Clone)]
+enum SyncTarget {
+    Builtin(ToolTarget),
+    Template(TemplateTarget),
+}
+
+impl SyncTarget {
+    /// A built-in tool, unless targets.toml redefines it, or a target from targets.toml
+    fn resolve(name: &str) -> Result<SyncTarget, String> {
+        Self::resolve_with(name, load_template_targets)
+    }
+
+    fn resolve_with(
+        name: &str,
+        load_templates: impl FnOnce() -> Result<BTreeMap<String, TemplateTarget>, String>,
+    ) -> Result<SyncTarget, String> {
+        if let Ok(target) = ToolTarget::from_str(name, true) {
+            // A broken targets.toml must not take the built-in targets down with it
+            let template = match load_templates() {
+                Ok(mut templates) => templates.remove(&target_name(target)),
+                Err(e) => {
+                    eprintln!("Warning: {}; using the built-in {} target", e, target_name(target));
+                    None
+                }
+            };
+            return Ok(template.map_or(SyncTarget::Builtin(target), SyncTarget::Template));
+        }
+
+        let mut templates = load_templates()?;
+        templates.remove(name).map(SyncTarget::Template).ok_or_else(|| {
+            let mut known: Vec<String> = ToolTarget::value_variants().iter().map(|t| target_name(*t)).collect();
+            known.extend(templates.into_keys().filter(|name| ToolTarget::from_str(name, true).is_err()));
+            format!("Unknown target '{}' (available: {})", name, known.join(", "))
+        })
+    }
+
+    /// Name recorded in .promption.lock and used for metadata overrides
+    fn name(&self) -> String {
+        match self {
+            SyncTarget::Builtin(target) => target_name(*target),
+            SyncTarget::Template(template) => template.name.clone(),
+        }
+    }
+
+    /// Name shown in progress messages
+    fn label(&self) -> String {
+        match self {
+            SyncTarget::Builtin(target) => format!("{:?}", target),
+            SyncTarget::Template(template) => match ToolTarget::from_str(&template.name, false) {
+                Ok(target) => format!("{:?}", target),
+                Err(_) => template.name.clone(),
+            },
+        }
+    }
+}
+
+/// Parses target names: --help lists the built-in targets, and a name that is
+/// neither built in nor in targets.toml fails before the database is opened
+#[derive(Clone)]
+struct TargetNameParser;
+
+impl clap::builder::TypedValueParser for TargetNameParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<String, clap::Error> {
+        let name = clap::builder::StringValueParser::new().parse_ref(cmd, arg, value)?;
+        if ToolTarget::from_str(&name, true).is_ok() {
+            return Ok(name);
+        }
+        match SyncTarget::resolve(&name) {
+            Ok(_) => Ok(name),
+            Err(e) => Err(clap::Error::raw(clap::error::ErrorKind::InvalidValue, format!("{}\n", e)).with_cmd(cmd)),
+        }
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        Some(Box::new(ToolTarget::value_variants().iter().filter_map(ValueEnum::to_possible_value)))
+    }
+}
+
+const TARGETS_FILE: &str = "targets.toml";
+
+/// How a template target lays out the selected items
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum TemplateMode {
+    /// One file per item
+    #[default]
+    Files,
+    /// Managed sections of a single document
+    Combined,
+}
+
+/// A target defined by templates, in targets.toml or by a built-in tool whose
+/// layout needs nothing more. Templates may use `{id}`, `{name}`, `{slug}`,
+/// `{type}`, `{description}`, `{globs}`, `{activation}` and `{content}`. A
+/// targets.toml entry named after a built-in tool replaces that tool's layout.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+struct TemplateTarget {
+    #[serde(skip)]
+    name: String,
+    #[serde(default)]
+    mode: TemplateMode,
+    /// Path of every item (files mode) or of the combined document
+    path: Option<String>,
+    /// Per-type paths for files mode, keyed by skill/rule/workflow
+    #[serde(default)]
+    paths: BTreeMap<String, String>,
+    /// YAML frontmatter, without the `---` fences (files mode)
+    frontmatter: Option<String>,
+    /// Per-type frontmatter for files mode, keyed by skill/rule/workflow
+    #[serde(default)]
+    frontmatters: BTreeMap<String, String>,
+    /// Section body in combined mode, defaults to "## {name}\n\n{content}"
+    section: Option<String>,
+    /// Item types to sync, defaults to all
+    #[serde(default)]
+    types: Vec<ItemTypeArg>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct TargetsFile {
+    #[serde(default)]
+    targets: BTreeMap<String, TemplateTarget>,
+}
+
+fn get_targets_path() -> Result<PathBuf, String> {
+    Ok(get_app_dir()?.join(TARGETS_FILE))
+}
+
+fn load_template_targets() -> Result<BTreeMap<String, TemplateTarget>, String> {
+    // Without a config dir there is nowhere to define custom targets
+    match get_targets_path() {
+        Ok(path) => read_template_targets(&path),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn read_template_targets(path: &Path) -> Result<BTreeMap<String, TemplateTarget>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_template_targets(&content, &path.display().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("Could not read {}: {}", path.display(), e)),
+    }
+}
+
+fn parse_template_targets(content: &str, source: &str) -> Result<BTreeMap<String, TemplateTarget>, String> {
+    let file: TargetsFile = toml::from_str(content).map_err(|e| format!("Invalid {}: {}", source, e))?;
+
+    let mut targets = BTreeMap::new();
+    for (name, mut target) in file.targets {
+        let has_path = match target.mode {
+            TemplateMode::Files => target.path.is_some() || !target.paths.is_empty(),
+            TemplateMode::Combined => target.path.is_some(),
+        };
+        if !has_path {
+            return Err(format!("{}: target '{}' needs a path", source, name));
+        }
+        let unknown_type = target
+            .paths
+            .keys()
+            .chain(target.frontmatters.keys())
+            .find(|k| !["skill", "rule", "workflow"].contains(&k.as_str()));
+        if let Some(key) = unknown_type {
+            return Err(format!("{}: target '{}' has a template for unknown type '{}'", source, name, key));
+        }
+        target.name = name.clone();
+        targets.insert(name, target);
+    }
+    Ok(targets)
+}
+
+/// Substitute `{placeholder}`s in a single pass, so braces inside item content are
+/// never expanded. Frontmatter values are YAML-quoted when needed.
+fn render_template(template: &str, item: &Item, meta: &ItemMetadata, yaml: bool) -> String {
+    let scalar = |value: &str| if yaml { yaml_scalar(value) } else { value.to_string() };
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let value = match &after[..end] {
+                "id" => scalar(&item.id),
+                "name" => scalar(&item.name),
+                "slug" => slugify(&item.name),
+                "type" => item.item_type.clone(),
+                "description" => scalar(meta.description_or(&item.name)),
+                "globs" => scalar(&meta.globs.join(",")),
+                "activation" => meta.activation().map(|a| a.as_str().to_string()).unwrap_or_default(),
+                "content" => item.content.clone(),
+                _ => return None,
+            };
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Render an item's path, e.g. `.acme/{type}s/{slug}.md`
+fn template_path(template: &str, item: &Item, meta: &ItemMetadata) -> std::io::Result<PathBuf> {
+    project_path(PathBuf::from(render_template(template, item, meta, false)))
+}
+
+/// Target paths must stay inside the project
+fn project_path(path: PathBuf) -> std::io::Result<PathBuf> {
+    let inside = path
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+    if !inside || path.as_os_str().is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Target path '{}' is outside the project", path.display()),
+        ));
+    }
+    Ok(path)
+}
+
+fn sync_template(items: &[Item], target: &TemplateTarget, plan: &mut SyncPlan) -> std::io::Result<()> {
+    let selected: Vec<&Item> = items
+        .iter()
+        .filter(|item| {
+            let wanted = target.types.is_empty() || target.types.iter().any(|t| t.as_str() == item.item_type);
+            if !wanted {
+                eprintln!(
+                    "Warning: Skipping {} '{}' ('{}' does not sync {}s)",
+                    item.item_type, item.name, target.name, item.item_type
+                );
+            }
+            wanted
+        })
+        .collect();
+
+    match target.mode {
+        TemplateMode::Files => {
+            for item in selected {
+                let meta = item.metadata.for_target_name(&target.name);
+                let Some(path_template) = target.paths.get(&item.item_type).or(target.path.as_ref()) else {
+                    eprintln!(
+                        "Warning: Skipping {} '{}' (no path for {}s in '{}')",
+                        item.item_type, item.name, item.item_type, target.name
+                    );
+                    continue;
+                };
+                let path = template_path(path_template, item, &meta)?;
+                let content = match target.frontmatters.get(&item.item_type).or(target.frontmatter.as_ref()) {
+                    Some(frontmatter) => format!(
+                        "---\n{}\n---\n\n{}",
+                        render_template(frontmatter.trim(), item, &meta, true),
+                        item.content
+                    ),
+                    None => item.content.clone(),
+                };
+                plan.write_item(item, path, content);
+            }
+        }
+        TemplateMode::Combined => {
+            let path_template = target.path.as_deref().unwrap_or_default();
+            let section = target.section.as_deref().unwrap_or("## {name}\n\n{content}");
+
+            // A fixed path is always rewritten, so deselected sections get removed
+            let mut documents: BTreeMap<PathBuf, (Vec<ManagedBlock>, Vec<&Item>)> = BTreeMap::new();
+            if !path_template.contains('{') {
+                documents.insert(project_path(PathBuf::from(path_template))?, Default::default());
+            }
+            for item in selected {
+                let meta = item.metadata.for_target_name(&target.name);
+                let (blocks, sources) = documents.entry(template_path(path_template, item, &meta)?).or_default();
+                blocks.push(ManagedBlock {
+                    id: item.id.clone(),
+                    body: render_template(section, item, &meta, false).trim_end().to_string(),
+                });
+                sources.push(item);
+            }
+
+            for (path, (blocks, sources)) in documents {
+                write_managed_document(plan, path, blocks, &sources)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The CLI spelling of a target, e.g. `cursor`
+fn target_name(target: ToolTarget) -> String {
+    target
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_else(|| format!("{:?}", target).to_lowercase())
+}
+
+/// Lockfile target for a sync: Roo/Kilo mode-specific syncs are tracked
+/// separately so pruning one mode never touches another.
+fn lock_target_name(target: &str, mode: Option<&str>) -> String {
+    match mode {
+        Some(mode) => format!("{}:{}", target, mode),
+        None => target.to_string(),
+    }
+}
+
+fn validate_mode_arg(target: &SyncTarget, mode: Option<&str>) -> Result<(), String> {
+    let Some(mode) = mode else {
+        return Ok(());
+    };
+    if !matches!(target, SyncTarget::Builtin(ToolTarget::Roo | ToolTarget::Kilo)) {
+        return Err("--mode is only supported by the roo and kilo targets".to_string());
+    }
+    if mode.is_empty() || !mode.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid mode slug '{}' (use letters, digits and '-')", mode));
+    }
+    Ok(())
+}
+
+fn sync_items(items: &[Item], target: &SyncTarget, mode: Option<&str>, plan: &mut SyncPlan) -> std::io::Result<()> {
+    let target = match target {
+        SyncTarget::Builtin(target) => *target,
+        SyncTarget::Template(template) => return sync_template(items, template, plan),
+    };
+    match target {
+        ToolTarget::Antigravity => sync_antigravity(items, plan),
+        ToolTarget::Cursor => sync_cursor(items, plan),
+        ToolTarget::Windsurf => sync_windsurf(items, plan),
+        ToolTarget::Opencode => sync_opencode(items, plan),
+        ToolTarget::Cline => sync_cline(items, plan),
+        ToolTarget::Copilot => sync_copilot(items, plan),
+        ToolTarget::Claude => sync_claude(items, plan),
+        ToolTarget::AgentsMd => sync_agents_md(items, plan),
+        ToolTarget::Gemini => sync_gemini(items, plan),
+        ToolTarget::Continue => sync_continue(items, plan),
+        ToolTarget::Aider => sync_aider(items, plan),
+        ToolTarget::Roo | ToolTarget::Kilo => sync_roo(items, target, mode, plan),
+        ToolTarget::Zed => sync_zed(items, plan),
+        ToolTarget::Jetbrains => sync_jetbrains(items, plan),
+        ToolTarget::Kiro => sync_kiro(items, plan),
+    }
+}
+
+fn sync_antigravity(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let agent_path = PathBuf::from(".agent");
+    let skills_path = agent_path.join("skills");
+    let rules_path = agent_path.join("rules");
+    let workflows_path = agent_path.join("workflows");
+
+    for item in items {
+        let slug = slugify(&item.name);
+        let meta = item.metadata.for_target(ToolTarget::Antigravity);
+        match item.item_type.as_str() {
+            "skill" => {
+                plan.write_item(item, skills_path.join(&slug).join("SKILL.md"), skill_document(item, &slug, &meta));
+            }
+            "rule" => {
+                let content = trigger_frontmatter(item, &meta, Activation::Always) + &item.content;
+                plan.write_item(item, rules_path.join(format!("{}.md", slug)), content);
+            }
+            "workflow" => {
+                let content = format!(
+                    "---\ndescription: {}\n---\n\n{}",
+                    yaml_scalar(meta.description_or(&item.name)),
+                    item.content
+                );
+                plan.write_item(item, workflows_path.join(format!("{}.md", slug)), content);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn sync_cursor(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let rules_path = PathBuf::from(".cursor/rules");
+
+    for item in items {
+        let slug = slugify(&item.name);
+        // Cursor uses .mdc for rules with frontmatter
+        if item.item_type == "rule" {
+            let meta = item.metadata.for_target(ToolTarget::Cursor);
+            let activation = meta.effective_activation(&item.name, Activation::Always);
+            let description = match activation {
+                Activation::Manual => String::new(),
+                _ => yaml_scalar(meta.description_or(&item.name)),
+            };
+            let globs = match activation {
+                Activation::Glob => meta.globs.join(","),
+                _ => String::new(),
+            };
+            let content = format!(
+                "---\ndescription: {}\nglobs: {}\nalwaysApply: {}\n---\n\n{}",
+                description,
+                globs,
+                activation == Activation::Always,
+                item.content
+            );
+            plan.write_item(item, rules_path.join(format!("{}.mdc", slug)), content);
+        } else {
+            // Treat skills/workflows as regular markdown docs for context
+            plan.write_item(item, rules_path.join(format!("{}.md", slug)), &item.content);
+        }
+    }
+    Ok(())
+}
+
+fn sync_windsurf(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let rules_path = PathBuf::from(".windsurf/rules");
+    let skills_path = PathBuf::from(".windsurf/skills");
+
+    for item in items {
+        let slug = slugify(&item.name);
+        let meta = item.metadata.for_target(ToolTarget::Windsurf);
+        match item.item_type.as_str() {
+            "skill" => {
+                plan.write_item(item, skills_path.join(&slug).join("SKILL.md"), skill_document(item, &slug, &meta));
+            }
+            _ => {
+                // Rules and Workflows go to rules/; workflows only load on request
+                let default = if item.item_type == "rule" { Activation::Always } else { Activation::Manual };
+                let content = trigger_frontmatter(item, &meta, default) + &item.content;
+                plan.write_item(item, rules_path.join(format!("{}.md", slug)), content);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sync_opencode(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let rule_path = ".opencode/rules/{slug}.md".to_string();
+    let template = TemplateTarget {
+        name: target_name(ToolTarget::Opencode),
+        paths: BTreeMap::from([
+            ("skill".to_string(), ".opencode/skills/{slug}/SKILL.md".to_string()),
+            ("rule".to_string(), rule_path.clone()),
+            ("workflow".to_string(), rule_path),
+        ]),
+        frontmatters: BTreeMap::from([("skill".to_string(), "name: {slug}\ndescription: {description}".to_string())]),
+        ..Default::default()
+    };
+    sync_template(items, &template, plan)
+}
+
+fn sync_cline(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let rules_path = PathBuf::from(".clinerules");
+    let skills_path = PathBuf::from(".cline/skills");
+
+    for item in items {
+        let slug = slugify(&item.name);
+        let meta = item.metadata.for_target(ToolTarget::Cline);
+        match item.item_type.as_str() {
+            "skill" => {
+                plan.write_item(item, skills_path.join(&slug).join("SKILL.md"), skill_document(item, &slug, &meta));
+            }
+            _ => {
+                // Cline only supports path-scoped rules; everything else is always loaded
+                let content = match meta.activation() {
+                    Some(Activation::Glob) if !meta.globs.is_empty() => {
+                        let paths: Vec<String> = meta.globs.iter().map(|g| format!("  - {}", yaml_scalar(g))).collect();
+                        format!("---\npaths:\n{}\n---\n\n{}", paths.join("\n"), item.content)
+                    }
+                    _ => item.content.clone(),
+                };
+                plan.write_item(item, rules_path.join(format!("{}.md", slug)), content);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sync_copilot(items: &[Item], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let template = TemplateTarget {
+        name: target_name(ToolTarget::Copilot),
+        mode: TemplateMode::Combined,
+        path: Some(".github/copilot-instructions.md".to_string()),
+        section: Some("# {name}\n\n{content}".to_string()),
+        ..Default::default()
+    };
+    sync_template(items, &template, plan)
+}
+
+/// Run CLI commands. Returns true if a command was executed, false if GUI should launch.
+pub fn run() -> bool {
+    let cli = Cli::parse();
+
+    let db_path = get_db_path(cli.db.as_deref(), cli.library.as_deref());
+
+    // If no subcommand, return false to launch GUI
+    let command = match cli.command {
+        Some(cmd) => cmd,
+        None => {
+            if cli.db.is_some() || cli.library.is_some() || std::env::var_os(DB_ENV).is_some() {
+                match db_path {
+                    Ok(path) => {
+                        // The SQL plugin resolves relative URLs inside the app dir, not the cwd
+                        let path = match std::env::current_dir() {
+                            Ok(cwd) => cwd.join(path),
+                            Err(_) => path,
+                        };
+                        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                            let _ = fs::create_dir_all(parent);
+                        }
+                        let _ = GUI_DB_PATH.set(path);
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            return false;
+        }
+    };
+
+    if let Commands::Libraries = command {
+        match list_libraries() {
+            Ok(names) => {
+                let current = cli.library.as_deref().unwrap_or("");
+                let marker = |selected: bool| if selected { "*" } else { " " };
+                let default_selected = current.is_empty() && cli.db.is_none() && std::env::var_os(DB_ENV).is_none();
+                println!("{} default", marker(default_selected));
+                for name in names {
+                    println!("{} {}", marker(name == current), name);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return true;
+    }
+
+    let db_path = match db_path {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let created = !db_path.exists();
+    if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Err(e) = fs::create_dir_all(parent) {
+            eprintln!("Error: Could not create {}: {}", parent.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    let conn = match Connection::open(&db_path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: Could not open database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let applied = match migrations::apply(&conn) {
+        Ok(applied) => applied,
+        Err(e) => {
+            eprintln!("Error: Could not migrate database at {:?}: {}", db_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Commands::Init = command {
+        let version = migrations::latest_version();
+        if created {
+            println!("✓ Created library at {} (schema version {})", db_path.display(), version);
+        } else if applied.is_empty() {
+            println!("Library at {} is up to date (schema version {})", db_path.display(), version);
+        } else {
+            println!("✓ Upgraded library at {} to schema version {}", db_path.display(), version);
+        }
+        if !created {
+            for migration in &applied {
+                println!("  + {} {}", migration.version, migration.description);
+            }
+        }
+        return true;
+    }
+
+    match command {
+        Commands::Sync {
+            select,
+            target,
+            dry_run,
+            diff,
+            prune,
+            mode,
+        } => {
+            let target = match SyncTarget::resolve(&target).and_then(|t| validate_mode_arg(&t, mode.as_deref()).map(|_| t)) {
+                Ok(target) => target,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if select.is_empty() {
+                eprintln!("Error: No items selected. Use --ids, --names, --match, --type, --tag or --all");
+                std::process::exit(1);
+            }
+
+            let (items, unmatched) = match resolve_item_selectors(&conn, &select) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            warn_unmatched_selectors(&unmatched);
+
+            if items.is_empty() {
+                eprintln!("Warning: No items matched the provided selection");
+                std::process::exit(1);
+            }
+
+            println!(
+                "Syncing {} item(s) to {} configuration...",
+                items.len(),
+                target.label()
+            );
+
+            let mut plan = SyncPlan::default();
+            let target_id = lock_target_name(&target.name(), mode.as_deref());
+            let result = sync_items(&items, &target, mode.as_deref(), &mut plan)
+                .and_then(|_| if prune { plan_prune(&mut plan, &SyncLock::load()?, &target_id) } else { Ok(()) })
+                .and_then(|_| plan.run(&target_id, dry_run, diff));
+            match result {
+                Ok(()) => {
+                    if !(dry_run || diff) {
+                        println!("\nDone! {} item(s) synced.", items.len());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error writing files: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Install {
+            file,
+            dry_run,
+            diff,
+            prune,
+        } => {
+            let config = match ProjectConfig::load(&file) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    eprintln!("Declare the items, agents and targets for this project in {}.", PROJECT_CONFIG_FILE);
+                    std::process::exit(1);
+                }
+            };
+
+            let targets = match config.targets() {
+                Ok(targets) => targets,
+                Err(e) => {
+                    eprintln!("Error in {}: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            let item_selectors = config.item_selectors();
+            let agent_selectors = config.agent_selectors();
+
+            if !item_selectors.is_empty() && targets.is_empty() {
+                eprintln!("Error: {} selects items but lists no targets", file.display());
+                std::process::exit(1);
+            }
+
+            let mut items: Vec<Item> = Vec::new();
+            let mut unmatched: Vec<String> = Vec::new();
+            for select in &item_selectors {
+                match resolve_item_selectors(&conn, select) {
+                    Ok((found, missing)) => {
+                        for item in found {
+                            if !items.iter().any(|i| i.id == item.id) {
+                                items.push(item);
+                            }
+                        }
+                        unmatched.extend(missing);
+                    }
+                    Err(e) => {
+                        eprintln!("Database error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let agents = if agent_selectors.is_empty() {
+                vec![]
+            } else {
+                match resolve_agent_selectors(&conn, &agent_selectors) {
+                    Ok((agents, missing)) => {
+                        unmatched.extend(missing.into_iter().map(|m| format!("[agents] {}", m)));
+                        agents
+                    }
+                    Err(e) => {
+                        eprintln!("Database error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            };
+
+            warn_unmatched_selectors(&unmatched);
+
+            if items.is_empty() && agents.is_empty() {
+                eprintln!("Warning: {} matched no items or agents", file.display());
+                std::process::exit(1);
+            }
+
+            let prune = prune || config.prune;
+            let target_names: Vec<String> = targets.iter().map(|t| t.name()).collect();
+            for target in targets {
+                println!("Installing {} item(s) to {} configuration...", items.len(), target.label());
+
+                let mut plan = SyncPlan::default();
+                let target_id = target.name();
+                let result = sync_items(&items, &target, None, &mut plan)
+                    .and_then(|_| if prune { plan_prune(&mut plan, &SyncLock::load()?, &target_id) } else { Ok(()) })
+                    .and_then(|_| plan.run(&target_id, dry_run, diff));
+                if let Err(e) = result {
+                    eprintln!("Error writing files: {}", e);
+                    std::process::exit(1);
+                }
+                println!();
+            }
+
+            let agent_targets = match config.agent_targets() {
+                Ok(targets) => targets,
+                Err(e) => {
+                    eprintln!("Error in {}: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            for target in agent_targets.iter().filter(|_| !agents.is_empty()) {
+                println!("Installing {} agent(s) to {}...", agents.len(), target.destination());
+
+                let mut plan = SyncPlan::default();
+                let result = sync_agents(&agents, *target, &mut plan)
+                    .and_then(|_| plan.run(target.lock_name(), dry_run, diff));
+                if let Err(e) = result {
+                    eprintln!("Error writing {}: {}", target.destination(), e);
+                    std::process::exit(1);
+                }
+                println!();
+            }
+
+            if prune {
+                let mut listed = target_names;
+                if !agent_selectors.is_empty() {
+                    listed.extend(agent_targets.iter().map(|t| t.lock_name().to_string()));
+                }
+                let dropped = match SyncLock::load() {
+                    Ok(lock) => dropped_lock_targets(&lock, &listed),
+                    Err(e) => {
+                        eprintln!("Error reading {}: {}", LOCK_FILE, e);
+                        std::process::exit(1);
+                    }
+                };
+                for target_id in dropped {
+                    println!("Removing files synced to {} (no longer in {})...", target_id, file.display());
+
+                    let mut plan = SyncPlan::default();
+                    let result = SyncLock::load()
+                        .and_then(|lock| plan_prune(&mut plan, &lock, &target_id))
+                        .and_then(|_| plan.run(&target_id, dry_run, diff));
+                    if let Err(e) = result {
+                        eprintln!("Error removing files: {}", e);
+                        std::process::exit(1);
+                    }
+                    println!();
+                }
+            }
+
+            if !(dry_run || diff) {
+                println!("Done! {} item(s) and {} agent(s) installed.", items.len(), agents.len());
+            }
+        }
+        Commands::Clean { target, dry_run, mode } => {
+            let (target_id, label) = match SyncTarget::resolve(&target) {
+                Ok(resolved) => {
+                    if let Err(e) = validate_mode_arg(&resolved, mode.as_deref()) {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                    (lock_target_name(&resolved.name(), mode.as_deref()), resolved.label())
+                }
+                // A target since removed from targets.toml can still be cleaned by name
+                Err(_) if mode.is_none() => (target.clone(), target),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let mut plan = SyncPlan::default();
+
+            match SyncLock::load().and_then(|lock| plan_prune(&mut plan, &lock, &target_id)) {
+                Ok(()) if plan.deletes.is_empty() && plan.writes.is_empty() => {
+                    println!("Nothing to clean for {}.", label);
+                }
+                Ok(()) => {
+                    let count = plan.deletes.len() + plan.writes.len();
+                    println!("Cleaning {} file(s) synced to {}...", count, label);
+                    if let Err(e) = plan.run(&target_id, dry_run, false) {
+                        eprintln!("Error removing files: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", LOCK_FILE, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::List {
+            r#type,
+            tags,
+            tag_mode,
+        } => {
+            let type_filter = r#type.as_deref();
+
+            let result = get_all_items(&conn, type_filter).and_then(|items| {
+                if tags.is_empty() {
+                    Ok(items)
+                } else {
+                    filter_items_by_tags(&conn, items, &tags, tag_mode)
+                }
+            });
+
+            match result {
+                Ok(items) => {
+                    if items.is_empty() {
+                        println!("No items found.");
+                        return true;
+                    }
+
+                    println!("{:<36}  {:<10}  {}", "ID", "TYPE", "NAME");
+                    println!("{}", "-".repeat(70));
+
+                    for item in items {
+                        println!("{:<36}  {:<10}  {}", item.id, item.item_type, item.name);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::SyncAgents {
+            select,
+            target,
+            dry_run,
+            diff,
+        } => {
+            if select.is_empty() {
+                eprintln!("Error: No agents selected. Use --ids, --names, --match or --all");
+                std::process::exit(1);
+            }
+
+            match resolve_agent_selectors(&conn, &select) {
+                Ok((agents, unmatched)) => {
+                    warn_unmatched_selectors(&unmatched);
+
+                    if agents.is_empty() {
+                        eprintln!("Warning: No agents matched the provided selection");
+                        std::process::exit(1);
+                    }
+
+                    println!("Syncing {} agent(s) to {}...", agents.len(), target.destination());
+
+                    let mut plan = SyncPlan::default();
+                    let result = sync_agents(&agents, target, &mut plan)
+                        .and_then(|_| plan.run(target.lock_name(), dry_run, diff));
+                    match result {
+                        Ok(()) => {
+                            if !(dry_run || diff) {
+                                println!("\nDone! {} agent(s) synced to {}.", agents.len(), target.destination());
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("Error writing {}: {}", target.destination(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::ImportAgents { file, on_conflict, dry_run } => {
+            println!("Importing agents from {}...", file.display());
+            match import_agents_cli(&conn, &file, on_conflict, dry_run) {
+                Ok((created, overwritten, skipped)) => {
+                    let summary = format!("{} created, {} overwritten, {} skipped", created, overwritten, skipped);
+                    if dry_run {
+                        println!("\nDry run: {}. The library was not changed.", summary);
+                    } else {
+                        println!("\nDone! {}.", summary);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to import agents: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::ListAgents => {
+            match get_all_agents(&conn) {
+                Ok(agents) => {
+                    if agents.is_empty() {
+                        println!("No agents found.");
+                        return true;
+                    }
+
+                    println!("{:<36}  {:<10}  {}", "ID", "MODE", "NAME");
+                    println!("{}", "-".repeat(70));
+
+                    for agent in agents {
+                        println!("{:<36}  {:<10}  {}", agent.id, agent.mode, agent.name);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::CreateAgent {
+            name,
+            mode,
+            model,
+            prompt,
+            prompt_file,
+            tools,
+            permissions,
+            format,
+        } => {
+            println!("Creating agent '{}'...", name);
+
+            match create_agent_cli(
+                &conn,
+                &name,
+                mode,
+                model.as_deref(),
+                prompt.as_deref(),
+                prompt_file,
+                &tools,
+                &permissions,
+            ) {
+                Ok(agent) => {
+                    match format {
+                        OutputFormat::Json => {
+                            print_agent_json(&agent);
+                        }
+                        OutputFormat::Text => {
+                            println!("\n✓ Agent created successfully!");
+                            println!("  ID: {}", agent.id);
+                            println!("  Name: {}", agent.name);
+                            println!("  Mode: {}", agent.mode);
+                            if let Some(m) = &agent.model {
+                                println!("  Model: {}", m);
                             }
                             if let Some(p) = &agent.prompt_content {
                                 println!("  Prompt: {} characters", p.len());
@@ -700,420 +3518,2333 @@ pub fn run() -> bool {
                                     }
                                 }
                             }
-                            if let Some(p) = &agent.permissions_config {
-                                if let Ok(perms_json) = serde_json::from_str::<Value>(p) {
-                                    if let Some(obj) = perms_json.as_object() {
-                                        println!("  Permissions: {} configured", obj.len());
-                                    }
+                            if let Some(p) = &agent.permissions_config {
+                                if let Ok(perms_json) = serde_json::from_str::<Value>(p) {
+                                    if let Some(obj) = perms_json.as_object() {
+                                        println!("  Permissions: {} configured", obj.len());
+                                    }
+                                }
+                            }
+                            println!("\nTo sync to opencode.json, run:");
+                            println!("  promption sync-agents --ids={}", agent.id);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to create agent: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::GetAgent { id, format } => {
+            match get_agent_by_id_or_name(&conn, &id) {
+                Ok(Some(agent)) => match format {
+                    OutputFormat::Json => {
+                        print_agent_json(&agent);
+                    }
+                    OutputFormat::Text => {
+                        println!("Agent: {}", agent.name);
+                        println!("  ID: {}", agent.id);
+                        println!("  Mode: {}", agent.mode);
+                        if let Some(m) = &agent.model {
+                            println!("  Model: {}", m);
+                        }
+                        if let Some(p) = &agent.prompt_content {
+                            println!("  Prompt: {} characters", p.len());
+                        }
+                        if let Some(t) = &agent.tools_config {
+                            if let Ok(tools_json) = serde_json::from_str::<Value>(t) {
+                                println!("  Tools: {}", serde_json::to_string_pretty(&tools_json).unwrap());
+                            }
+                        }
+                        if let Some(p) = &agent.permissions_config {
+                            if let Ok(perms_json) = serde_json::from_str::<Value>(p) {
+                                println!("  Permissions: {}", serde_json::to_string_pretty(&perms_json).unwrap());
+                            }
+                        }
+                    }
+                },
+                Ok(None) => {
+                    eprintln!("Agent '{}' not found", id);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::UpdateAgent {
+            id,
+            name,
+            mode,
+            model,
+            clear_model,
+            prompt,
+            prompt_file,
+            clear_prompt,
+            tools,
+            clear_tools,
+            permissions,
+            clear_permissions,
+        } => {
+            println!("Updating agent '{}'...", id);
+
+            match update_agent_cli(
+                &conn,
+                &id,
+                name.as_deref(),
+                mode,
+                model.as_deref(),
+                clear_model,
+                prompt.as_deref(),
+                prompt_file,
+                clear_prompt,
+                &tools,
+                clear_tools,
+                &permissions,
+                clear_permissions,
+            ) {
+                Ok(()) => {
+                    println!("✓ Agent updated successfully!");
+                }
+                Err(e) => {
+                    eprintln!("Failed to update agent: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::DeleteAgent { id } => {
+            match delete_agent_cli(&conn, &id) {
+                Ok(()) => {
+                    println!("✓ Agent '{}' deleted successfully", id);
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete agent: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::CreateItem {
+            name,
+            item_type,
+            content,
+            content_file,
+            metadata,
+            format,
+        } => {
+            if format == OutputFormat::Text {
+                println!("Creating {} '{}'...", item_type.as_str(), name);
+            }
+
+            match create_item_cli(&conn, &name, item_type, &content, content_file, &metadata) {
+                Ok(item) => match format {
+                    OutputFormat::Json => {
+                        print_item_json(&conn, &item);
+                    }
+                    OutputFormat::Text => {
+                        println!("\n✓ Item created successfully!");
+                        println!("  ID: {}", item.id);
+                        println!("  Name: {}", item.name);
+                        println!("  Type: {}", item.item_type);
+                        println!("  Content: {} characters", item.content.len());
+                        println!("\nTo sync to your project, run:");
+                        println!("  promption sync --ids={}", item.id);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Failed to create item: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::GetItem { id, format } => {
+            match get_item_by_id_or_name(&conn, &id) {
+                Ok(Some(item)) => match format {
+                    OutputFormat::Json => {
+                        print_item_json(&conn, &item);
+                    }
+                    OutputFormat::Text => {
+                        println!("Item: {}", item.name);
+                        println!("  ID: {}", item.id);
+                        println!("  Type: {}", item.item_type);
+                        if let Some(activation) = item.metadata.activation() {
+                            println!("  Activation: {}", activation.as_str());
+                        }
+                        if !item.metadata.globs.is_empty() {
+                            println!("  Globs: {}", item.metadata.globs.join(", "));
+                        }
+                        if let Ok(tags) = get_item_tags(&conn, &item.id) {
+                            if !tags.is_empty() {
+                                let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+                                println!("  Tags: {}", names.join(", "));
+                            }
+                        }
+                        println!("  Content:\n");
+                        println!("{}", item.content);
+                    }
+                },
+                Ok(None) => {
+                    eprintln!("Item '{}' not found", id);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::UpdateItem {
+            id,
+            name,
+            item_type,
+            content,
+            content_file,
+            metadata,
+        } => {
+            println!("Updating item '{}'...", id);
+
+            match update_item_cli(
+                &conn,
+                &id,
+                name.as_deref(),
+                item_type,
+                content.as_deref(),
+                content_file,
+                &metadata,
+            ) {
+                Ok(()) => {
+                    println!("✓ Item updated successfully!");
+                }
+                Err(e) => {
+                    eprintln!("Failed to update item: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::DeleteItem { id } => {
+            match delete_item_cli(&conn, &id) {
+                Ok(()) => {
+                    println!("✓ Item '{}' deleted successfully", id);
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete item: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import {
+            bundle: Some(bundle),
+            on_conflict,
+            dry_run,
+            ..
+        } => {
+            let bundle_data = match read_bundle(&bundle) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error reading '{}': {}", bundle.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            println!(
+                "Importing {} item(s), {} tag(s) and {} agent(s) from {}...",
+                bundle_data.items.len(),
+                bundle_data.tags.len(),
+                bundle_data.agents.len(),
+                bundle.display()
+            );
+            match import_bundle_cli(&conn, &bundle_data, on_conflict, dry_run) {
+                Ok((items, agents)) => {
+                    if dry_run {
+                        println!("\nDry run: items {}; agents {}. The library was not changed.", items, agents);
+                    } else {
+                        println!("\nDone! Items {}; agents {}.", items, agents);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to import bundle: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import { from: Some(from), path, dry_run, .. } => {
+            let found = match scan_import_source(&path, from) {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            if found.is_empty() {
+                println!("No {:?} configuration found in {}", from, path.display());
+                return true;
+            }
+
+            println!("Importing {} item(s) from {:?} configuration...", found.len(), from);
+            match import_items_cli(&conn, &found, dry_run) {
+                Ok((imported, skipped)) => {
+                    if dry_run {
+                        println!("\nDry run: {} to import, {} skipped. The library was not changed.", imported, skipped);
+                    } else {
+                        println!("\nDone! {} item(s) imported, {} skipped.", imported, skipped);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import { .. } => unreachable!("clap requires a bundle or --from"),
+        Commands::Export {
+            select,
+            agents,
+            all_agents,
+            out,
+        } => {
+            if select.is_empty() && agents.is_empty() && !all_agents {
+                eprintln!("Error: Nothing selected. Use --ids, --names, --match, --type, --tag, --all, --agents or --all-agents");
+                std::process::exit(1);
+            }
+
+            let items = if select.is_empty() {
+                Vec::new()
+            } else {
+                match resolve_item_selectors(&conn, &select) {
+                    Ok((items, unmatched)) => {
+                        warn_unmatched_selectors(&unmatched);
+                        items
+                    }
+                    Err(e) => {
+                        eprintln!("Database error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            };
+
+            let selected_agents = if all_agents {
+                get_all_agents(&conn)
+            } else {
+                agents
+                    .iter()
+                    .filter_map(|id_or_name| match get_agent_by_id_or_name(&conn, id_or_name) {
+                        Ok(None) => {
+                            eprintln!("Warning: Agent '{}' not found", id_or_name);
+                            None
+                        }
+                        result => result.transpose(),
+                    })
+                    .collect()
+            };
+            let selected_agents = match selected_agents {
+                Ok(agents) => agents,
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            if items.is_empty() && selected_agents.is_empty() {
+                eprintln!("Warning: Nothing matched the provided selection");
+                std::process::exit(1);
+            }
+
+            let bundle = match build_bundle(&conn, &items, &selected_agents) {
+                Ok(bundle) => bundle,
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = write_bundle(&out, &bundle) {
+                eprintln!("Error writing '{}': {}", out.display(), e);
+                std::process::exit(1);
+            }
+            println!(
+                "Exported {} item(s), {} tag(s) and {} agent(s) to {}",
+                bundle.items.len(),
+                bundle.tags.len(),
+                bundle.agents.len(),
+                out.display()
+            );
+        }
+        Commands::Init => unreachable!("handled after migrating"),
+        Commands::Libraries => unreachable!("handled before opening the database"),
+        Commands::Status { format } => {
+            let lock = match SyncLock::load() {
+                Ok(lock) => lock,
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", LOCK_FILE, e);
+                    std::process::exit(1);
+                }
+            };
+
+            if lock.entries.is_empty() {
+                println!("No files recorded in {}. Run `promption sync` first.", LOCK_FILE);
+                return true;
+            }
+
+            let mut statuses = Vec::new();
+            for entry in &lock.entries {
+                match check_lock_entry(&conn, entry) {
+                    Ok(status) => statuses.push((entry, status)),
+                    Err(e) => {
+                        eprintln!("Database error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            match format {
+                OutputFormat::Json => {
+                    let json_entries: Vec<Value> = statuses
+                        .iter()
+                        .map(|(entry, status)| {
+                            json!({
+                                "path": entry.path,
+                                "target": entry.target,
+                                "status": status.label(),
+                                "file": status.file.as_str(),
+                                "outdated": status.outdated,
+                                "removed": status.removed,
+                            })
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&json_entries).unwrap());
+                }
+                OutputFormat::Text => {
+                    println!("Checking {} file(s) recorded in {}...\n", statuses.len(), LOCK_FILE);
+
+                    for (entry, status) in &statuses {
+                        println!("  {:<9} {:<16} {}", status.label(), entry.target, entry.path);
+                        if status.file == FileState::Modified {
+                            println!("      edited by hand since the last sync");
+                        }
+                        if !status.outdated.is_empty() {
+                            println!("      updated in library: {}", status.outdated.join(", "));
+                        }
+                        if !status.removed.is_empty() {
+                            println!("      no longer in library: {}", status.removed.join(", "));
+                        }
+                    }
+
+                    let count = |f: &dyn Fn(&EntryStatus) -> bool| statuses.iter().filter(|(_, s)| f(s)).count();
+                    println!(
+                        "\n{} up to date, {} edited by hand, {} deleted, {} outdated, {} orphaned.",
+                        count(&|s| s.is_clean()),
+                        count(&|s| s.file == FileState::Modified),
+                        count(&|s| s.file == FileState::Deleted),
+                        count(&|s| !s.outdated.is_empty()),
+                        count(&|s| !s.removed.is_empty()),
+                    );
+                }
+            }
+
+            if statuses.iter().any(|(_, status)| !status.is_clean()) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Search {
+            query,
+            r#type,
+            tags,
+            tag_mode,
+            limit,
+            raw,
+            format,
+        } => {
+            if let Some(t) = &r#type {
+                if !["skill", "rule", "workflow", "agent"].contains(&t.as_str()) {
+                    eprintln!("Error: Invalid type '{}'. Use skill, rule, workflow or agent.", t);
+                    std::process::exit(1);
+                }
+            }
+
+            let query = query.join(" ");
+            let fts_query = if raw { query.clone() } else { build_fts_query(&query) };
+            let highlight = if format == OutputFormat::Text && std::io::stdout().is_terminal() {
+                ("\x1b[1;33m", "\x1b[0m")
+            } else {
+                ("**", "**")
+            };
+
+            let result = search_library(&conn, &fts_query, r#type.as_deref(), highlight).and_then(|hits| {
+                if tags.is_empty() {
+                    return Ok(hits);
+                }
+                let tagged = get_item_ids_with_tags(&conn, &tags, tag_mode)?;
+                Ok(hits.into_iter().filter(|hit| tagged.contains(&hit.id)).collect())
+            });
+
+            match result {
+                Ok(mut hits) => {
+                    hits.truncate(limit);
+
+                    match format {
+                        OutputFormat::Json => {
+                            let json_hits: Vec<Value> = hits
+                                .iter()
+                                .map(|hit| {
+                                    json!({
+                                        "id": hit.id,
+                                        "type": hit.entity_type,
+                                        "name": hit.name,
+                                        "snippet": hit.snippet,
+                                        "score": hit.score,
+                                    })
+                                })
+                                .collect();
+                            println!("{}", serde_json::to_string_pretty(&json_hits).unwrap());
+                        }
+                        OutputFormat::Text => {
+                            if hits.is_empty() {
+                                println!("No results for '{}'.", query);
+                                return true;
+                            }
+
+                            for (i, hit) in hits.iter().enumerate() {
+                                println!("{:>3}. [{}] {}  ({})", i + 1, hit.entity_type, hit.name, hit.id);
+                                let snippet = hit.snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+                                if !snippet.is_empty() {
+                                    println!("     {}", snippet);
                                 }
                             }
-                            println!("\nTo sync to opencode.json, run:");
-                            println!("  promption sync-agents --ids={}", agent.id);
                         }
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to create agent: {}", e);
-                    std::process::exit(1);
+                Err(rusqlite::Error::SqliteFailure(_, Some(msg))) if msg.contains("fts5") => {
+                    eprintln!("Error: Invalid search query: {}", msg);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Tags { action } => match action {
+            TagCommands::List { format } => match get_all_tags(&conn) {
+                Ok(tags) => match format {
+                    OutputFormat::Json => {
+                        print_tags_json(&tags);
+                    }
+                    OutputFormat::Text => {
+                        if tags.is_empty() {
+                            println!("No tags found.");
+                            return true;
+                        }
+
+                        println!("{:<36}  {:<9}  {:<6}  NAME", "ID", "COLOR", "SYSTEM");
+                        println!("{}", "-".repeat(70));
+
+                        for tag in tags {
+                            let system = if tag.is_system { "yes" } else { "" };
+                            println!("{:<36}  {:<9}  {:<6}  {}", tag.id, tag.color, system, tag.name);
+                        }
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            TagCommands::Create { name, color } => match create_tag_cli(&conn, &name, &color) {
+                Ok(tag) => {
+                    println!("✓ Tag '{}' created ({})", tag.name, tag.id);
+                }
+                Err(e) => {
+                    eprintln!("Failed to create tag: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            TagCommands::Delete { id } => match delete_tag_cli(&conn, &id) {
+                Ok(()) => {
+                    println!("✓ Tag '{}' deleted successfully", id);
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete tag: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            TagCommands::Rename { id, name } => match rename_tag_cli(&conn, &id, &name) {
+                Ok(()) => {
+                    println!("✓ Tag '{}' renamed to '{}'", id, name);
+                }
+                Err(e) => {
+                    eprintln!("Failed to rename tag: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            TagCommands::Recolor { id, color } => match recolor_tag_cli(&conn, &id, &color) {
+                Ok(()) => {
+                    println!("✓ Tag '{}' color set to {}", id, color);
+                }
+                Err(e) => {
+                    eprintln!("Failed to recolor tag: {}", e);
+                    std::process::exit(1);
+                }
+            },
+        },
+        Commands::Items { action } => {
+            let (id, tags, attach) = match action {
+                ItemCommands::Tag { id, tags } => (id, tags, true),
+                ItemCommands::Untag { id, tags } => (id, tags, false),
+            };
+
+            match set_item_tags_cli(&conn, &id, &tags, attach) {
+                Ok(item) => {
+                    let names: Vec<String> = get_item_tags(&conn, &item.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| t.name)
+                        .collect();
+                    let summary = if names.is_empty() {
+                        "(none)".to_string()
+                    } else {
+                        names.join(", ")
+                    };
+                    println!("✓ Tags for '{}': {}", item.name, summary);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update item tags: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    true
+}
+
+fn get_agents_by_ids(conn: &Connection, ids: &[String]) -> SqliteResult<Vec<Agent>> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents WHERE id IN ({})",
+        placeholders.join(", ")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let params: Vec<&dyn rusqlite::ToSql> = ids.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+
+    let agents = stmt.query_map(params.as_slice(), |row| {
+        Ok(Agent {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            mode: row.get(2)?,
+            model: row.get(3)?,
+            prompt_content: row.get(4)?,
+            tools_config: row.get(5)?,
+            permissions_config: row.get(6)?,
+        })
+    })?;
+
+    agents.collect()
+}
+
+fn get_agents_by_names(conn: &Connection, names: &[String]) -> SqliteResult<Vec<Agent>> {
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let placeholders: Vec<String> = names.iter().map(|_| "?".to_string()).collect();
+    let query = format!(
+        "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents WHERE name IN ({})",
+        placeholders.join(", ")
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let params: Vec<&dyn rusqlite::ToSql> = names.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+
+    let agents = stmt.query_map(params.as_slice(), |row| {
+        Ok(Agent {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            mode: row.get(2)?,
+            model: row.get(3)?,
+            prompt_content: row.get(4)?,
+            tools_config: row.get(5)?,
+            permissions_config: row.get(6)?,
+        })
+    })?;
+
+    agents.collect()
+}
+
+fn get_agents_matching(conn: &Connection, pattern: &str) -> SqliteResult<Vec<Agent>> {
+    let agents = get_all_agents(conn)?;
+    Ok(agents
+        .into_iter()
+        .filter(|agent| name_matches_glob(&agent.name, pattern))
+        .collect())
+}
+
+fn get_all_agents(conn: &Connection) -> SqliteResult<Vec<Agent>> {
+    let query = "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents ORDER BY updated_at DESC";
+    let mut stmt = conn.prepare(query)?;
+
+    let agents = stmt.query_map([], |row| {
+        Ok(Agent {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            mode: row.get(2)?,
+            model: row.get(3)?,
+            prompt_content: row.get(4)?,
+            tools_config: row.get(5)?,
+            permissions_config: row.get(6)?,
+        })
+    })?;
+
+    agents.collect()
+}
+
+fn get_agent_by_id_or_name(conn: &Connection, id_or_name: &str) -> SqliteResult<Option<Agent>> {
+    // Try by ID first
+    let query = "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents WHERE id = ? OR name = ?";
+    let mut stmt = conn.prepare(query)?;
+    
+    let mut agents = stmt.query_map([id_or_name, id_or_name], |row| {
+        Ok(Agent {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            mode: row.get(2)?,
+            model: row.get(3)?,
+            prompt_content: row.get(4)?,
+            tools_config: row.get(5)?,
+            permissions_config: row.get(6)?,
+        })
+    })?;
+
+    agents.next().transpose()
+}
+
+fn create_agent_cli(
+    conn: &Connection,
+    name: &str,
+    mode: AgentModeArg,
+    model: Option<&str>,
+    prompt: Option<&str>,
+    prompt_file: bool,
+    tools: &[String],
+    permissions: &[String],
+) -> SqliteResult<Agent> {
+    // Validate name
+    if let Err(e) = validate_agent_name(name) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    // Check if agent with this name already exists
+    let existing = get_agent_by_id_or_name(conn, name)?;
+    if existing.is_some() {
+        eprintln!("Error: Agent with name '{}' already exists", name);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    // Generate ID
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    // Read prompt if file
+    let prompt_content = if prompt_file {
+        if let Some(path) = prompt {
+            match fs::read_to_string(path) {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    eprintln!("Error reading prompt file '{}': {}", path, e);
+                    return Err(rusqlite::Error::InvalidQuery);
+                }
+            }
+        } else {
+            None
+        }
+    } else {
+        prompt.map(String::from)
+    };
+
+    // Parse tools and permissions
+    let tools_config = parse_tools(tools)?;
+    let permissions_config = parse_permissions(permissions)?;
+
+    // Insert into database
+    conn.execute(
+        "INSERT INTO agents (id, name, mode, model, prompt_content, tools_config, permissions_config, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            id,
+            name,
+            mode.as_str(),
+            model,
+            prompt_content,
+            tools_config,
+            permissions_config,
+            now,
+            now,
+        ],
+    )?;
+
+    Ok(Agent {
+        id,
+        name: name.to_string(),
+        mode: mode.as_str().to_string(),
+        model: model.map(String::from),
+        prompt_content,
+        tools_config,
+        permissions_config,
+    })
+}
+
+/// Read `{file:path}` prompt references relative to the config file; anything
+/// else is an inline prompt.
+fn resolve_opencode_prompt(value: &str, base: &Path) -> std::io::Result<String> {
+    let Some(reference) = value.trim().strip_prefix("{file:").and_then(|v| v.strip_suffix('}')) else {
+        return Ok(value.to_string());
+    };
+    let path = match reference.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => base.join(reference),
+    };
+    fs::read_to_string(&path)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Import the `agent` map of an opencode.json. Returns (created, overwritten, skipped).
+fn import_agents_cli(
+    conn: &Connection,
+    file: &Path,
+    policy: ConflictPolicy,
+    dry_run: bool,
+) -> SqliteResult<(usize, usize, usize)> {
+    let config: Value = match fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error reading '{}': {}", file.display(), e);
+            return Err(rusqlite::Error::InvalidQuery);
+        }
+    };
+    let Some(agents) = config.get("agent").and_then(Value::as_object) else {
+        eprintln!("Error: '{}' has no \"agent\" section", file.display());
+        return Err(rusqlite::Error::InvalidQuery);
+    };
+
+    let base = file.parent().unwrap_or(Path::new("."));
+    let (mut created, mut overwritten, mut skipped) = (0, 0, 0);
+
+    for (name, definition) in agents {
+        if let Err(e) = validate_agent_name(name) {
+            eprintln!("Warning: Skipping agent '{}': {}", name, e);
+            skipped += 1;
+            continue;
+        }
+
+        // OpenCode's "all" (usable both ways) is closest to a primary agent here
+        let mode = match definition.get("mode").and_then(Value::as_str) {
+            Some("primary") | Some("all") => "primary",
+            _ => "subagent",
+        };
+        let model = definition.get("model").and_then(Value::as_str);
+        let prompt_content = match definition.get("prompt").and_then(Value::as_str) {
+            Some(prompt) => match resolve_opencode_prompt(prompt, base) {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    eprintln!("Warning: Skipping agent '{}': could not read prompt {}", name, e);
+                    skipped += 1;
+                    continue;
+                }
+            },
+            None => None,
+        };
+        // OpenCode documents "permission"; sync-agents writes "permissions", so accept both
+        let tools_config = definition.get("tools").filter(|v| v.is_object()).map(Value::to_string);
+        let permissions_config = definition
+            .get("permission")
+            .or_else(|| definition.get("permissions"))
+            .filter(|v| v.is_object())
+            .map(Value::to_string);
+
+        let existing = get_agent_by_id_or_name(conn, name)?;
+        let target_name = match (&existing, policy) {
+            (None, _) => name.clone(),
+            (Some(_), ConflictPolicy::Skip) => {
+                println!("  = {} (already exists)", name);
+                skipped += 1;
+                continue;
+            }
+            (Some(_), ConflictPolicy::Overwrite) => name.clone(),
+            (Some(_), ConflictPolicy::Rename) => {
+                let mut n = 2;
+                while get_agent_by_id_or_name(conn, &format!("{}-{}", name, n))?.is_some() {
+                    n += 1;
+                }
+                format!("{}-{}", name, n)
+            }
+        };
+
+        let now = chrono::Utc::now().to_rfc3339();
+        match existing.filter(|_| policy == ConflictPolicy::Overwrite) {
+            Some(agent) => {
+                if !dry_run {
+                    conn.execute(
+                        "UPDATE agents SET mode = ?, model = ?, prompt_content = ?, tools_config = ?, permissions_config = ?, updated_at = ? WHERE id = ?",
+                        rusqlite::params![mode, model, prompt_content, tools_config, permissions_config, now, agent.id],
+                    )?;
+                }
+                println!("  ~ {} (overwritten)", name);
+                overwritten += 1;
+            }
+            None => {
+                if !dry_run {
+                    conn.execute(
+                        "INSERT INTO agents (id, name, mode, model, prompt_content, tools_config, permissions_config, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                        rusqlite::params![
+                            uuid::Uuid::new_v4().to_string(),
+                            target_name,
+                            mode,
+                            model,
+                            prompt_content,
+                            tools_config,
+                            permissions_config,
+                            now,
+                            now,
+                        ],
+                    )?;
+                }
+                if target_name == *name {
+                    println!("  + {}", name);
+                } else {
+                    println!("  + {} (as {})", name, target_name);
+                }
+                created += 1;
+            }
+        }
+    }
+    Ok((created, overwritten, skipped))
+}
+
+fn update_agent_cli(
+    conn: &Connection,
+    id: &str,
+    name: Option<&str>,
+    mode: Option<AgentModeArg>,
+    model: Option<&str>,
+    clear_model: bool,
+    prompt: Option<&str>,
+    prompt_file: bool,
+    clear_prompt: bool,
+    tools: &[String],
+    clear_tools: bool,
+    permissions: &[String],
+    clear_permissions: bool,
+) -> SqliteResult<()> {
+    // Verify agent exists
+    let agent = get_agent_by_id_or_name(conn, id)?;
+    if agent.is_none() {
+        eprintln!("Error: Agent '{}' not found", id);
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let agent = agent.unwrap();
+
+    // Validate name if provided
+    if let Some(new_name) = name {
+        if let Err(e) = validate_agent_name(new_name) {
+            eprintln!("Error: {}", e);
+            return Err(rusqlite::Error::InvalidQuery);
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut updates = vec!["updated_at = ?".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
+
+    // Name update
+    if let Some(new_name) = name {
+        updates.push("name = ?".to_string());
+        params.push(Box::new(new_name.to_string()));
+    }
+
+    // Mode update
+    if let Some(new_mode) = mode {
+        updates.push("mode = ?".to_string());
+        params.push(Box::new(new_mode.as_str().to_string()));
+    }
+
+    // Model update
+    if clear_model {
+        updates.push("model = NULL".to_string());
+    } else if let Some(new_model) = model {
+        updates.push("model = ?".to_string());
+        params.push(Box::new(new_model.to_string()));
+    }
+
+    // Prompt update
+    if clear_prompt {
+        updates.push("prompt_content = NULL".to_string());
+    } else if let Some(prompt_val) = prompt {
+        let prompt_content = if prompt_file {
+            match fs::read_to_string(prompt_val) {
+                Ok(content) => content,
+                Err(e) => {
+                    eprintln!("Error reading prompt file '{}': {}", prompt_val, e);
+                    return Err(rusqlite::Error::InvalidQuery);
+                }
+            }
+        } else {
+            prompt_val.to_string()
+        };
+        updates.push("prompt_content = ?".to_string());
+        params.push(Box::new(prompt_content));
+    }
+
+    // Tools update
+    if clear_tools {
+        updates.push("tools_config = NULL".to_string());
+    } else if !tools.is_empty() {
+        let tools_config = parse_tools(tools)?;
+        updates.push("tools_config = ?".to_string());
+        params.push(Box::new(tools_config));
+    }
+
+    // Permissions update
+    if clear_permissions {
+        updates.push("permissions_config = NULL".to_string());
+    } else if !permissions.is_empty() {
+        let permissions_config = parse_permissions(permissions)?;
+        updates.push("permissions_config = ?".to_string());
+        params.push(Box::new(permissions_config));
+    }
+
+    // Add agent ID as last parameter
+    params.push(Box::new(agent.id.clone()));
+
+    let sql = format!("UPDATE agents SET {} WHERE id = ?", updates.join(", "));
+    
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
+    conn.execute(&sql, params_refs.as_slice())?;
+
+    Ok(())
+}
+
+fn delete_agent_cli(conn: &Connection, id_or_name: &str) -> SqliteResult<()> {
+    let agent = get_agent_by_id_or_name(conn, id_or_name)?;
+    if agent.is_none() {
+        eprintln!("Error: Agent '{}' not found", id_or_name);
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let agent = agent.unwrap();
+
+    conn.execute("DELETE FROM agents WHERE id = ?", [&agent.id])?;
+    Ok(())
+}
+
+fn print_agent_json(agent: &Agent) {
+    let mut json_obj = serde_json::Map::new();
+    json_obj.insert("id".to_string(), json!(agent.id));
+    json_obj.insert("name".to_string(), json!(agent.name));
+    json_obj.insert("mode".to_string(), json!(agent.mode));
+    
+    if let Some(model) = &agent.model {
+        json_obj.insert("model".to_string(), json!(model));
+    }
+    
+    if let Some(prompt) = &agent.prompt_content {
+        json_obj.insert("prompt_content".to_string(), json!(prompt));
+    }
+    
+    if let Some(tools) = &agent.tools_config {
+        if let Ok(tools_json) = serde_json::from_str::<Value>(tools) {
+            json_obj.insert("tools_config".to_string(), tools_json);
+        }
+    }
+    
+    if let Some(perms) = &agent.permissions_config {
+        if let Ok(perms_json) = serde_json::from_str::<Value>(perms) {
+            json_obj.insert("permissions_config".to_string(), perms_json);
+        }
+    }
+    
+    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
+}
+
+fn get_item_by_id_or_name(conn: &Connection, id_or_name: &str) -> SqliteResult<Option<Item>> {
+    // An exact ID match wins; item names are not unique, so refuse to guess between several
+    let query = "SELECT id, name, content, item_type, metadata FROM items WHERE id = ? OR name = ? ORDER BY id = ? DESC, updated_at DESC";
+    let mut stmt = conn.prepare(query)?;
+
+    let items: Vec<Item> = stmt
+        .query_map([id_or_name, id_or_name, id_or_name], item_from_row)?
+        .collect::<SqliteResult<_>>()?;
+
+    match items.first() {
+        Some(first) if first.id != id_or_name && items.len() > 1 => {
+            eprintln!(
+                "Error: {} items are named '{}', use the ID instead:",
+                items.len(),
+                id_or_name
+            );
+            for item in &items {
+                eprintln!("  {}  {}", item.id, item.item_type);
+            }
+            Err(rusqlite::Error::InvalidQuery)
+        }
+        _ => Ok(items.into_iter().next()),
+    }
+}
+
+fn create_item_cli(
+    conn: &Connection,
+    name: &str,
+    item_type: ItemTypeArg,
+    content: &str,
+    content_file: bool,
+    metadata_args: &ItemMetadataArgs,
+) -> SqliteResult<Item> {
+    let content = match read_content_arg(content, content_file) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error reading content '{}': {}", content, e);
+            return Err(rusqlite::Error::InvalidQuery);
+        }
+    };
+
+    if let Err(e) = validate_item_input(Some(name), Some(&content)) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    let mut metadata = ItemMetadata::default();
+    if let Err(e) = metadata_args.apply(&mut metadata) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+    let metadata_json = metadata_to_sql(&metadata)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO items (id, name, content, item_type, metadata, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![id, name, content, item_type.as_str(), metadata_json, now, now],
+    )?;
+
+    Ok(Item {
+        id,
+        name: name.to_string(),
+        content,
+        item_type: item_type.as_str().to_string(),
+        metadata,
+    })
+}
+
+fn update_item_cli(
+    conn: &Connection,
+    id: &str,
+    name: Option<&str>,
+    item_type: Option<ItemTypeArg>,
+    content: Option<&str>,
+    content_file: bool,
+    metadata_args: &ItemMetadataArgs,
+) -> SqliteResult<()> {
+    let item = get_item_by_id_or_name(conn, id)?;
+    if item.is_none() {
+        eprintln!("Error: Item '{}' not found", id);
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let item = item.unwrap();
+
+    let content = match content {
+        Some(value) => match read_content_arg(value, content_file) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                eprintln!("Error reading content '{}': {}", value, e);
+                return Err(rusqlite::Error::InvalidQuery);
+            }
+        },
+        None => None,
+    };
+
+    if let Err(e) = validate_item_input(name, content.as_deref()) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut updates = vec!["updated_at = ?".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
+
+    if let Some(new_name) = name {
+        updates.push("name = ?".to_string());
+        params.push(Box::new(new_name.to_string()));
+    }
+
+    if let Some(new_type) = item_type {
+        updates.push("item_type = ?".to_string());
+        params.push(Box::new(new_type.as_str().to_string()));
+    }
+
+    if let Some(new_content) = content {
+        updates.push("content = ?".to_string());
+        params.push(Box::new(new_content));
+    }
+
+    if metadata_args.is_set() {
+        let mut metadata = item.metadata.clone();
+        if let Err(e) = metadata_args.apply(&mut metadata) {
+            eprintln!("Error: {}", e);
+            return Err(rusqlite::Error::InvalidQuery);
+        }
+        updates.push("metadata = ?".to_string());
+        params.push(Box::new(metadata_to_sql(&metadata)?));
+    }
+
+    params.push(Box::new(item.id.clone()));
+
+    let sql = format!("UPDATE items SET {} WHERE id = ?", updates.join(", "));
+
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
+    conn.execute(&sql, params_refs.as_slice())?;
+
+    Ok(())
+}
+
+/// Empty metadata is stored as NULL
+fn metadata_to_sql(metadata: &ItemMetadata) -> SqliteResult<Option<String>> {
+    if metadata.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(metadata)
+        .map(Some)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn delete_item_cli(conn: &Connection, id_or_name: &str) -> SqliteResult<()> {
+    let item = get_item_by_id_or_name(conn, id_or_name)?;
+    if item.is_none() {
+        eprintln!("Error: Item '{}' not found", id_or_name);
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let item = item.unwrap();
+
+    // Foreign keys are not enforced on this connection, so clear tag links explicitly
+    conn.execute("DELETE FROM item_tags WHERE item_id = ?", [&item.id])?;
+    conn.execute("DELETE FROM items WHERE id = ?", [&item.id])?;
+    Ok(())
+}
+
+fn print_item_json(conn: &Connection, item: &Item) {
+    let tags: Vec<String> = get_item_tags(conn, &item.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+
+    let json_obj = json!({
+        "id": item.id,
+        "name": item.name,
+        "item_type": item.item_type,
+        "content": item.content,
+        "metadata": item.metadata,
+        "tags": tags,
+    });
+
+    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
+}
+
+/// An item read from a project's config files, not yet in the library
+#[derive(Debug)]
+struct ImportedItem {
+    source: PathBuf,
+    name: String,
+    item_type: ItemTypeArg,
+    content: String,
+    metadata: ItemMetadata,
+}
+
+/// Items found in `root` for `source`, the inverse of the matching `sync_*` layout
+fn scan_import_source(root: &Path, source: ImportSource) -> std::io::Result<Vec<ImportedItem>> {
+    let mut found = Vec::new();
+    match source {
+        ImportSource::Cursor => {
+            let rules = root.join(".cursor/rules");
+            for path in files_with_extension(&rules, "mdc")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            // Plain markdown next to the rules is loaded as context too
+            for path in files_with_extension(&rules, "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            found.extend(import_single_file(&root.join(".cursorrules"), "Cursor Rules")?);
+        }
+        ImportSource::Windsurf => {
+            for path in files_with_extension(&root.join(".windsurf/rules"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            for path in files_with_extension(&root.join(".windsurf/workflows"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Workflow)?);
+            }
+            found.extend(import_skills(&root.join(".windsurf/skills"))?);
+        }
+        ImportSource::Cline => {
+            let rules = root.join(".clinerules");
+            if rules.is_file() {
+                found.extend(import_single_file(&rules, "Cline Rules")?);
+            } else {
+                for path in files_with_extension(&rules, "md")? {
+                    found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+                }
+                for path in files_with_extension(&rules.join("workflows"), "md")? {
+                    found.extend(import_markdown(&path, ItemTypeArg::Workflow)?);
                 }
             }
+            found.extend(import_skills(&root.join(".cline/skills"))?);
         }
-        Commands::GetAgent { id, format } => {
-            match get_agent_by_id_or_name(&conn, &id) {
-                Ok(Some(agent)) => match format {
-                    OutputFormat::Json => {
-                        print_agent_json(&agent);
-                    }
-                    OutputFormat::Text => {
-                        println!("Agent: {}", agent.name);
-                        println!("  ID: {}", agent.id);
-                        println!("  Mode: {}", agent.mode);
-                        if let Some(m) = &agent.model {
-                            println!("  Model: {}", m);
-                        }
-                        if let Some(p) = &agent.prompt_content {
-                            println!("  Prompt: {} characters", p.len());
-                        }
-                        if let Some(t) = &agent.tools_config {
-                            if let Ok(tools_json) = serde_json::from_str::<Value>(t) {
-                                println!("  Tools: {}", serde_json::to_string_pretty(&tools_json).unwrap());
-                            }
-                        }
-                        if let Some(p) = &agent.permissions_config {
-                            if let Ok(perms_json) = serde_json::from_str::<Value>(p) {
-                                println!("  Permissions: {}", serde_json::to_string_pretty(&perms_json).unwrap());
-                            }
-                        }
-                    }
-                },
-                Ok(None) => {
-                    eprintln!("Agent '{}' not found", id);
-                    std::process::exit(1);
-                }
-                Err(e) => {
-                    eprintln!("Database error: {}", e);
-                    std::process::exit(1);
+        ImportSource::Opencode => {
+            for path in files_with_extension(&root.join(".opencode/rules"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            for dir in [".opencode/command", ".opencode/commands"] {
+                for path in files_with_extension(&root.join(dir), "md")? {
+                    found.extend(import_markdown(&path, ItemTypeArg::Workflow)?);
                 }
             }
+            found.extend(import_skills(&root.join(".opencode/skills"))?);
         }
-        Commands::UpdateAgent {
-            id,
-            name,
-            mode,
-            model,
-            clear_model,
-            prompt,
-            prompt_file,
-            clear_prompt,
-            tools,
-            clear_tools,
-            permissions,
-            clear_permissions,
-        } => {
-            println!("Updating agent '{}'...", id);
+        ImportSource::Antigravity => {
+            for path in files_with_extension(&root.join(".agent/rules"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            for path in files_with_extension(&root.join(".agent/workflows"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Workflow)?);
+            }
+            found.extend(import_skills(&root.join(".agent/skills"))?);
+        }
+        ImportSource::Copilot => {
+            let instructions = root.join(".github/copilot-instructions.md");
+            if let Some(text) = read_import_text(&instructions)? {
+                found.extend(import_managed_document(&instructions, &text, "Copilot Instructions"));
+            }
+            for path in files_with_extension(&root.join(".github/instructions"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Rule)?);
+            }
+            for path in files_with_extension(&root.join(".github/prompts"), "md")? {
+                found.extend(import_markdown(&path, ItemTypeArg::Workflow)?);
+            }
+        }
+    }
+    found.retain(|item| !item.content.trim().is_empty());
+    Ok(found)
+}
 
-            match update_agent_cli(
-                &conn,
-                &id,
-                name.as_deref(),
-                mode,
-                model.as_deref(),
-                clear_model,
-                prompt.as_deref(),
-                prompt_file,
-                clear_prompt,
-                &tools,
-                clear_tools,
-                &permissions,
-                clear_permissions,
-            ) {
-                Ok(()) => {
-                    println!("✓ Agent updated successfully!");
-                }
-                Err(e) => {
-                    eprintln!("Failed to update agent: {}", e);
-                    std::process::exit(1);
-                }
+/// Files directly inside `dir` with the given extension, sorted by name
+fn files_with_extension(dir: &Path, extension: &str) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `<dir>/<name>/SKILL.md` skill folders
+fn import_skills(dir: &Path) -> std::io::Result<Vec<ImportedItem>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path().join("SKILL.md");
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut skills = Vec::new();
+    for path in paths {
+        skills.extend(import_markdown(&path, ItemTypeArg::Skill)?);
+    }
+    Ok(skills)
+}
+
+/// Text of a file to import; files that are not UTF-8 are skipped with a warning
+fn read_import_text(path: &Path) -> std::io::Result<Option<String>> {
+    match read_existing(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            eprintln!("Warning: Skipping {} (not UTF-8 text)", path.display());
+            Ok(None)
+        }
+        result => result,
+    }
+}
+
+fn import_single_file(path: &Path, name: &str) -> std::io::Result<Option<ImportedItem>> {
+    Ok(read_import_text(path)?.map(|text| ImportedItem {
+        source: path.to_path_buf(),
+        name: name.to_string(),
+        item_type: ItemTypeArg::Rule,
+        content: text.trim_end().to_string(),
+        metadata: ItemMetadata::default(),
+    }))
+}
+
+/// One markdown file with optional frontmatter. The name comes from a readable
+/// `name`/`title` field, else from the file (or skill folder) name.
+fn import_markdown(path: &Path, item_type: ItemTypeArg) -> std::io::Result<Option<ImportedItem>> {
+    let Some(text) = read_import_text(path)? else {
+        return Ok(None);
+    };
+    let (fields, body) = parse_frontmatter(&text);
+
+    let stem = if path.file_name().is_some_and(|n| n == "SKILL.md") {
+        path.parent().and_then(Path::file_name)
+    } else {
+        path.file_stem()
+    }
+    .map(|n| n.to_string_lossy().to_string())
+    .unwrap_or_default();
+    // Copilot's react.instructions.md and review.prompt.md; other dots are part of the name
+    let stem = [".instructions", ".prompt"]
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))
+        .unwrap_or(&stem)
+        .to_string();
+
+    let field = |key: &str| fields.get(key).and_then(|v| v.first()).filter(|v| !v.is_empty()).cloned();
+    let name = match field("name").or_else(|| field("title")) {
+        Some(name) if slugify(&name) != name => name,
+        Some(slug) => title_from_slug(&slug),
+        None => title_from_slug(&stem),
+    };
+
+    let mut metadata = metadata_from_frontmatter(&fields);
+    if metadata.description.as_deref() == Some(name.as_str()) {
+        metadata.description = None;
+    }
+
+    Ok(Some(ImportedItem {
+        source: path.to_path_buf(),
+        name,
+        item_type,
+        content: body.trim_end().to_string(),
+        metadata,
+    }))
+}
+
+/// A shared document: each managed block becomes an item named by its heading,
+/// and any hand-written text around them becomes one more item.
+fn import_managed_document(path: &Path, text: &str, name: &str) -> Vec<ImportedItem> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut found = Vec::new();
+    let mut outside: Vec<&str> = Vec::new();
+
+    for part in parse_managed_document(&lines) {
+        match part {
+            DocumentPart::Block { body, .. } => {
+                let body = body.join("\n");
+                let (heading, content) = match body.split_once('\n') {
+                    Some((first, rest)) if first.starts_with('#') => (first.trim_start_matches('#').trim(), rest),
+                    _ => ("", body.as_str()),
+                };
+                found.push(ImportedItem {
+                    source: path.to_path_buf(),
+                    name: if heading.is_empty() { name.to_string() } else { heading.to_string() },
+                    item_type: ItemTypeArg::Rule,
+                    content: content.trim().to_string(),
+                    metadata: ItemMetadata::default(),
+                });
             }
+            DocumentPart::Text(line) => outside.push(line),
         }
-        Commands::DeleteAgent { id } => {
-            match delete_agent_cli(&conn, &id) {
-                Ok(()) => {
-                    println!("✓ Agent '{}' deleted successfully", id);
-                }
-                Err(e) => {
-                    eprintln!("Failed to delete agent: {}", e);
-                    std::process::exit(1);
-                }
+    }
+
+    let outside = outside.join("\n");
+    if !outside.trim().is_empty() {
+        found.push(ImportedItem {
+            source: path.to_path_buf(),
+            name: name.to_string(),
+            item_type: ItemTypeArg::Rule,
+            content: outside.trim().to_string(),
+            metadata: ItemMetadata::default(),
+        });
+    }
+    found
+}
+
+/// "react-rules" -> "React Rules"
+fn title_from_slug(slug: &str) -> String {
+    slug.split(['-', '_', ' '])
+        .filter(|w| !w.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Split `---` frontmatter from a markdown body. Values are read as strings or
+/// lists (flow `[a, b]` or block `- a`), which covers what the supported tools write.
+fn parse_frontmatter(text: &str) -> (BTreeMap<String, Vec<String>>, &str) {
+    let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (fields, text);
+    };
+
+    let mut offset = 0;
+    let mut close = None;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            close = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((header_end, body_start)) = close else {
+        return (fields, text);
+    };
+
+    let unquote = |value: &str| -> String {
+        let value = value.trim();
+        if value.starts_with('"') {
+            serde_json::from_str(value).unwrap_or_else(|_| value.trim_matches('"').to_string())
+        } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+            value[1..value.len() - 1].replace("''", "'")
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut current: Option<String> = None;
+    for line in rest[..header_end].lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let (Some(key), Some(entry)) = (&current, trimmed.strip_prefix("- ")) {
+            fields.entry(key.clone()).or_default().push(unquote(entry));
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_string();
+        let value = value.trim();
+        let values = if value.is_empty() {
+            vec![]
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            inner.split(',').map(unquote).filter(|v| !v.is_empty()).collect()
+        } else {
+            vec![unquote(value)]
+        };
+        current = value.is_empty().then(|| key.clone());
+        fields.insert(key, values);
+    }
+
+    (fields, rest[body_start..].trim_start_matches(['\r', '\n']))
+}
+
+/// Map the activation fields each tool uses back onto item metadata
+fn metadata_from_frontmatter(fields: &BTreeMap<String, Vec<String>>) -> ItemMetadata {
+    let first = |key: &str| fields.get(key).and_then(|v| v.first()).map(String::as_str);
+
+    let globs: Vec<String> = ["globs", "paths", "applyTo", "fileMatchPattern"]
+        .iter()
+        .filter_map(|key| fields.get(*key))
+        .flatten()
+        .flat_map(|value| value.split(','))
+        .map(|glob| glob.trim().to_string())
+        .filter(|glob| !glob.is_empty())
+        .collect();
+    let description = first("description").filter(|d| !d.is_empty()).map(str::to_string);
+
+    let activation = match (first("trigger"), first("alwaysApply"), first("inclusion")) {
+        (Some("always_on"), _, _) | (_, Some("true"), _) | (_, _, Some("always")) => Some(Activation::Always),
+        (Some("glob"), _, _) | (_, _, Some("fileMatch")) => Some(Activation::Glob),
+        (Some("model_decision"), _, _) => Some(Activation::Model),
+        (Some("manual"), _, _) | (_, _, Some("manual")) => Some(Activation::Manual),
+        // Cursor: alwaysApply false means globs, then description, then manual
+        (_, Some("false"), _) if !globs.is_empty() => Some(Activation::Glob),
+        (_, Some("false"), _) if description.is_some() => Some(Activation::Model),
+        (_, Some("false"), _) => Some(Activation::Manual),
+        _ => None,
+    };
+
+    ItemMetadata {
+        activation,
+        globs,
+        description,
+        targets: BTreeMap::new(),
+    }
+}
+
+/// Insert imported items in one transaction, skipping any whose name or content
+/// is already in the library (or earlier in the same import). Returns (imported, skipped).
+fn import_items_cli(conn: &Connection, found: &[ImportedItem], dry_run: bool) -> SqliteResult<(usize, usize)> {
+    let tx = conn.unchecked_transaction()?;
+    let existing = get_all_items(&tx, None)?;
+    let mut names: HashSet<String> = existing.iter().map(|item| item.name.to_lowercase()).collect();
+    let mut contents: HashSet<String> = existing.iter().map(|item| content_hash(item.content.trim())).collect();
+    let (mut imported, mut skipped) = (0, 0);
+
+    for item in found {
+        let source = item.source.display();
+        let hash = content_hash(item.content.trim());
+        if names.contains(&item.name.to_lowercase()) {
+            println!("  = {} (an item named '{}' exists)", source, item.name);
+            skipped += 1;
+            continue;
+        }
+        if contents.contains(&hash) {
+            println!("  = {} (same content as an existing item)", source);
+            skipped += 1;
+            continue;
+        }
+        if let Err(e) = validate_item_input(Some(&item.name), Some(&item.content)) {
+            eprintln!("Warning: Skipping {}: {}", source, e);
+            skipped += 1;
+            continue;
+        }
+
+        if !dry_run {
+            let now = chrono::Utc::now().to_rfc3339();
+            tx.execute(
+                "INSERT INTO items (id, name, content, item_type, metadata, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                rusqlite::params![
+                    uuid::Uuid::new_v4().to_string(),
+                    item.name,
+                    item.content,
+                    item.item_type.as_str(),
+                    metadata_to_sql(&item.metadata)?,
+                    now,
+                    now
+                ],
+            )?;
+        }
+        println!("  + {} '{}' from {}", item.item_type.as_str(), item.name, source);
+        names.insert(item.name.to_lowercase());
+        contents.insert(hash);
+        imported += 1;
+    }
+    tx.commit()?;
+    Ok((imported, skipped))
+}
+
+const BUNDLE_FORMAT: &str = "promption-bundle";
+/// Bump when the layout changes; older versions must keep importing
+const BUNDLE_VERSION: u32 = 1;
+/// Name of the JSON document inside .tar.gz bundles
+const BUNDLE_ENTRY: &str = "bundle.json";
+
+/// A portable slice of the library. IDs only link entries within the bundle;
+/// imports assign fresh ones.
+#[derive(Serialize, Deserialize, Debug)]
+struct Bundle {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    exported_at: Option<String>,
+    #[serde(default)]
+    tags: Vec<BundleTag>,
+    #[serde(default)]
+    items: Vec<BundleItem>,
+    #[serde(default)]
+    agents: Vec<BundleAgent>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleTag {
+    id: String,
+    name: String,
+    color: String,
+    #[serde(default)]
+    is_system: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleItem {
+    id: String,
+    name: String,
+    item_type: ItemTypeArg,
+    content: String,
+    #[serde(default)]
+    metadata: ItemMetadata,
+    /// IDs of entries in `Bundle::tags`
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleAgent {
+    name: String,
+    mode: String,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    prompt_content: Option<String>,
+    #[serde(default)]
+    tools: Option<Value>,
+    #[serde(default)]
+    permissions: Option<Value>,
+}
+
+fn build_bundle(conn: &Connection, items: &[Item], agents: &[Agent]) -> SqliteResult<Bundle> {
+    let mut tags: Vec<BundleTag> = Vec::new();
+    let mut bundle_items = Vec::new();
+
+    for item in items {
+        let item_tags = get_item_tags(conn, &item.id)?;
+        for tag in &item_tags {
+            if !tags.iter().any(|t| t.id == tag.id) {
+                tags.push(BundleTag {
+                    id: tag.id.clone(),
+                    name: tag.name.clone(),
+                    color: tag.color.clone(),
+                    is_system: tag.is_system,
+                });
             }
         }
+        let Ok(item_type) = ItemTypeArg::from_str(&item.item_type, true) else {
+            continue;
+        };
+        bundle_items.push(BundleItem {
+            id: item.id.clone(),
+            name: item.name.clone(),
+            item_type,
+            content: item.content.clone(),
+            metadata: item.metadata.clone(),
+            tags: item_tags.into_iter().map(|t| t.id).collect(),
+        });
     }
 
-    true
+    let parse = |json: &Option<String>| json.as_deref().and_then(|j| serde_json::from_str(j).ok());
+    Ok(Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: Some(chrono::Utc::now().to_rfc3339()),
+        tags,
+        items: bundle_items,
+        agents: agents
+            .iter()
+            .map(|agent| BundleAgent {
+                name: agent.name.clone(),
+                mode: agent.mode.clone(),
+                model: agent.model.clone(),
+                prompt_content: agent.prompt_content.clone(),
+                tools: parse(&agent.tools_config),
+                permissions: parse(&agent.permissions_config),
+            })
+            .collect(),
+    })
 }
 
-fn get_agents_by_ids(conn: &Connection, ids: &[String]) -> SqliteResult<Vec<Agent>> {
-    if ids.is_empty() {
-        return Ok(vec![]);
+fn is_tarball(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_lowercase();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+fn write_bundle(path: &Path, bundle: &Bundle) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(bundle).map_err(std::io::Error::other)?;
+    if !is_tarball(path) {
+        return fs::write(path, json);
     }
 
-    let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
-    let query = format!(
-        "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents WHERE id IN ({})",
-        placeholders.join(", ")
-    );
+    let encoder = flate2::write::GzEncoder::new(fs::File::create(path)?, flate2::Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive.append_data(&mut header, BUNDLE_ENTRY, json.as_bytes())?;
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
 
-    let mut stmt = conn.prepare(&query)?;
-    let params: Vec<&dyn rusqlite::ToSql> = ids.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+fn read_bundle(path: &Path) -> Result<Bundle, String> {
+    let json = if is_tarball(path) {
+        let file = fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let mut json = None;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            if entry.path().map_err(|e| e.to_string())?.as_ref() == Path::new(BUNDLE_ENTRY) {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).map_err(|e| e.to_string())?;
+                json = Some(content);
+                break;
+            }
+        }
+        json.ok_or_else(|| format!("archive has no {}", BUNDLE_ENTRY))?
+    } else {
+        fs::read_to_string(path).map_err(|e| e.to_string())?
+    };
 
-    let agents = stmt.query_map(params.as_slice(), |row| {
-        Ok(Agent {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            mode: row.get(2)?,
-            model: row.get(3)?,
-            prompt_content: row.get(4)?,
-            tools_config: row.get(5)?,
-            permissions_config: row.get(6)?,
-        })
-    })?;
+    let bundle: Bundle = serde_json::from_str(&json).map_err(|e| format!("not a promption bundle ({})", e))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(format!("not a promption bundle (format '{}')", bundle.format));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "bundle version {} is newer than this promption supports ({}); please upgrade",
+            bundle.version, BUNDLE_VERSION
+        ));
+    }
+    Ok(bundle)
+}
 
-    agents.collect()
+/// Counts of what a bundle import did, per entry kind
+#[derive(Debug, Default)]
+struct ImportReport {
+    created: usize,
+    overwritten: usize,
+    skipped: usize,
 }
 
-fn get_all_agents(conn: &Connection) -> SqliteResult<Vec<Agent>> {
-    let query = "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents ORDER BY updated_at DESC";
-    let mut stmt = conn.prepare(query)?;
+impl std::fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} created, {} overwritten, {} skipped", self.created, self.overwritten, self.skipped)
+    }
+}
 
-    let agents = stmt.query_map([], |row| {
-        Ok(Agent {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            mode: row.get(2)?,
-            model: row.get(3)?,
-            prompt_content: row.get(4)?,
-            tools_config: row.get(5)?,
-            permissions_config: row.get(6)?,
-        })
-    })?;
+/// Merge a bundle into the library in one transaction. Tags are matched by
+/// name, items by name and type, agents by name; `policy` decides conflicts.
+fn import_bundle_cli(
+    conn: &Connection,
+    bundle: &Bundle,
+    policy: ConflictPolicy,
+    dry_run: bool,
+) -> SqliteResult<(ImportReport, ImportReport)> {
+    let tx = conn.unchecked_transaction()?;
+    let now = chrono::Utc::now().to_rfc3339();
 
-    agents.collect()
-}
+    // Bundle tag ID -> local tag ID, filled in as imported items use them
+    let mut tag_ids: std::collections::HashMap<&str, String> = std::collections::HashMap::new();
 
-fn get_agent_by_id_or_name(conn: &Connection, id_or_name: &str) -> SqliteResult<Option<Agent>> {
-    // Try by ID first
-    let query = "SELECT id, name, mode, model, prompt_content, tools_config, permissions_config FROM agents WHERE id = ? OR name = ?";
-    let mut stmt = conn.prepare(query)?;
-    
-    let mut agents = stmt.query_map([id_or_name, id_or_name], |row| {
-        Ok(Agent {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            mode: row.get(2)?,
-            model: row.get(3)?,
-            prompt_content: row.get(4)?,
-            tools_config: row.get(5)?,
-            permissions_config: row.get(6)?,
-        })
-    })?;
+    let mut items_report = ImportReport::default();
+    for item in &bundle.items {
+        if let Err(e) = validate_item_input(Some(&item.name), Some(&item.content)) {
+            eprintln!("Warning: Skipping item '{}': {}", item.name, e);
+            items_report.skipped += 1;
+            continue;
+        }
 
-    agents.next().transpose()
-}
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT id FROM items WHERE name = ? COLLATE NOCASE AND item_type = ? ORDER BY updated_at DESC LIMIT 1",
+                [&item.name, item.item_type.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let metadata = metadata_to_sql(&item.metadata)?;
 
-fn create_agent_cli(
-    conn: &Connection,
-    name: &str,
-    mode: AgentModeArg,
-    model: Option<&str>,
-    prompt: Option<&str>,
-    prompt_file: bool,
-    tools: &[String],
-    permissions: &[String],
-) -> SqliteResult<Agent> {
-    // Validate name
-    if let Err(e) = validate_agent_name(name) {
-        eprintln!("Error: {}", e);
-        return Err(rusqlite::Error::InvalidQuery);
-    }
+        let item_id = match (existing, policy) {
+            (Some(_), ConflictPolicy::Skip) => {
+                println!("  = {} '{}' (already exists)", item.item_type.as_str(), item.name);
+                items_report.skipped += 1;
+                continue;
+            }
+            (Some(id), ConflictPolicy::Overwrite) => {
+                tx.execute(
+                    "UPDATE items SET content = ?, metadata = ?, updated_at = ? WHERE id = ?",
+                    rusqlite::params![item.content, metadata, now, id],
+                )?;
+                println!("  ~ {} '{}' (overwritten)", item.item_type.as_str(), item.name);
+                items_report.overwritten += 1;
+                id
+            }
+            (existing, _) => {
+                let mut name = item.name.clone();
+                if existing.is_some() {
+                    let mut n = 2;
+                    while tx
+                        .query_row(
+                            "SELECT 1 FROM items WHERE name = ? COLLATE NOCASE AND item_type = ?",
+                            [&format!("{} {}", item.name, n), item.item_type.as_str()],
+                            |_| Ok(()),
+                        )
+                        .optional()?
+                        .is_some()
+                    {
+                        n += 1;
+                    }
+                    name = format!("{} {}", item.name, n);
+                }
+                let id = uuid::Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO items (id, name, content, item_type, metadata, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![id, name, item.content, item.item_type.as_str(), metadata, now, now],
+                )?;
+                if name == item.name {
+                    println!("  + {} '{}'", item.item_type.as_str(), name);
+                } else {
+                    println!("  + {} '{}' (as '{}')", item.item_type.as_str(), item.name, name);
+                }
+                items_report.created += 1;
+                id
+            }
+        };
 
-    // Check if agent with this name already exists
-    let existing = get_agent_by_id_or_name(conn, name)?;
-    if existing.is_some() {
-        eprintln!("Error: Agent with name '{}' already exists", name);
-        return Err(rusqlite::Error::InvalidQuery);
+        for tag_ref in &item.tags {
+            let Some(tag) = bundle.tags.iter().find(|t| &t.id == tag_ref) else {
+                eprintln!("Warning: Item '{}' references unknown bundle tag '{}'", item.name, tag_ref);
+                continue;
+            };
+            let tag_id = match tag_ids.get(tag.id.as_str()) {
+                Some(tag_id) => tag_id.clone(),
+                None => {
+                    let tag_id = import_bundle_tag(&tx, tag, policy)?;
+                    tag_ids.insert(tag.id.as_str(), tag_id.clone());
+                    tag_id
+                }
+            };
+            tx.execute(
+                "INSERT OR IGNORE INTO item_tags (item_id, tag_id) VALUES (?, ?)",
+                [&item_id, &tag_id],
+            )?;
+        }
     }
 
-    // Generate ID
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().to_rfc3339();
+    let mut agents_report = ImportReport::default();
+    for agent in &bundle.agents {
+        if let Err(e) = validate_agent_name(&agent.name) {
+            eprintln!("Warning: Skipping agent '{}': {}", agent.name, e);
+            agents_report.skipped += 1;
+            continue;
+        }
+        let mode = if agent.mode == "primary" { "primary" } else { "subagent" };
+        let tools = agent.tools.as_ref().map(Value::to_string);
+        let permissions = agent.permissions.as_ref().map(Value::to_string);
 
-    // Read prompt if file
-    let prompt_content = if prompt_file {
-        if let Some(path) = prompt {
-            match fs::read_to_string(path) {
-                Ok(content) => Some(content),
-                Err(e) => {
-                    eprintln!("Error reading prompt file '{}': {}", path, e);
-                    return Err(rusqlite::Error::InvalidQuery);
+        match (get_agent_by_id_or_name(&tx, &agent.name)?, policy) {
+            (Some(_), ConflictPolicy::Skip) => {
+                println!("  = agent '{}' (already exists)", agent.name);
+                agents_report.skipped += 1;
+            }
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                tx.execute(
+                    "UPDATE agents SET mode = ?, model = ?, prompt_content = ?, tools_config = ?, permissions_config = ?, updated_at = ? WHERE id = ?",
+                    rusqlite::params![mode, agent.model, agent.prompt_content, tools, permissions, now, existing.id],
+                )?;
+                println!("  ~ agent '{}' (overwritten)", agent.name);
+                agents_report.overwritten += 1;
+            }
+            (existing, _) => {
+                let mut name = agent.name.clone();
+                if existing.is_some() {
+                    let mut n = 2;
+                    while get_agent_by_id_or_name(&tx, &format!("{}-{}", agent.name, n))?.is_some() {
+                        n += 1;
+                    }
+                    name = format!("{}-{}", agent.name, n);
+                }
+                tx.execute(
+                    "INSERT INTO agents (id, name, mode, model, prompt_content, tools_config, permissions_config, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![
+                        uuid::Uuid::new_v4().to_string(),
+                        name,
+                        mode,
+                        agent.model,
+                        agent.prompt_content,
+                        tools,
+                        permissions,
+                        now,
+                        now,
+                    ],
+                )?;
+                if name == agent.name {
+                    println!("  + agent '{}'", name);
+                } else {
+                    println!("  + agent '{}' (as '{}')", agent.name, name);
                 }
+                agents_report.created += 1;
             }
-        } else {
-            None
         }
+    }
+
+    // A dry run goes through the same statements and then discards them
+    if dry_run {
+        tx.rollback()?;
     } else {
-        prompt.map(String::from)
-    };
+        tx.commit()?;
+    }
+    Ok((items_report, agents_report))
+}
 
-    // Parse tools and permissions
-    let tools_config = parse_tools(tools)?;
-    let permissions_config = parse_permissions(permissions)?;
+/// Local ID for a bundle tag, matched by name or created. Imported tags are
+/// never system tags, whatever the bundle says.
+fn import_bundle_tag(conn: &Connection, tag: &BundleTag, policy: ConflictPolicy) -> SqliteResult<String> {
+    match get_tag_by_id_or_name(conn, &tag.name)?.filter(|t| t.name.eq_ignore_ascii_case(&tag.name)) {
+        Some(existing) => {
+            if policy == ConflictPolicy::Overwrite && existing.color != tag.color {
+                conn.execute("UPDATE tags SET color = ? WHERE id = ?", [&tag.color, &existing.id])?;
+            }
+            Ok(existing.id)
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO tags (id, name, color, is_system) VALUES (?, ?, ?, 0)",
+                [&id, &tag.name, &tag.color],
+            )?;
+            Ok(id)
+        }
+    }
+}
 
-    // Insert into database
+fn create_tag_cli(conn: &Connection, name: &str, color: &str) -> SqliteResult<Tag> {
+    if let Err(e) = validate_tag_name(name).and_then(|_| validate_tag_color(color)) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    if get_tag_by_id_or_name(conn, name)?.is_some() {
+        eprintln!("Error: Tag '{}' already exists", name);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
     conn.execute(
-        "INSERT INTO agents (id, name, mode, model, prompt_content, tools_config, permissions_config, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-        rusqlite::params![
-            id,
-            name,
-            mode.as_str(),
-            model,
-            prompt_content,
-            tools_config,
-            permissions_config,
-            now,
-            now,
-        ],
+        "INSERT INTO tags (id, name, color, is_system) VALUES (?, ?, ?, 0)",
+        rusqlite::params![id, name, color],
     )?;
 
-    Ok(Agent {
+    Ok(Tag {
         id,
         name: name.to_string(),
-        mode: mode.as_str().to_string(),
-        model: model.map(String::from),
-        prompt_content,
-        tools_config,
-        permissions_config,
+        color: color.to_string(),
+        is_system: false,
     })
 }
 
-fn update_agent_cli(
-    conn: &Connection,
-    id: &str,
-    name: Option<&str>,
-    mode: Option<AgentModeArg>,
-    model: Option<&str>,
-    clear_model: bool,
-    prompt: Option<&str>,
-    prompt_file: bool,
-    clear_prompt: bool,
-    tools: &[String],
-    clear_tools: bool,
-    permissions: &[String],
-    clear_permissions: bool,
-) -> SqliteResult<()> {
-    // Verify agent exists
-    let agent = get_agent_by_id_or_name(conn, id)?;
-    if agent.is_none() {
-        eprintln!("Error: Agent '{}' not found", id);
-        return Err(rusqlite::Error::QueryReturnedNoRows);
-    }
-    let agent = agent.unwrap();
-
-    // Validate name if provided
-    if let Some(new_name) = name {
-        if let Err(e) = validate_agent_name(new_name) {
-            eprintln!("Error: {}", e);
-            return Err(rusqlite::Error::InvalidQuery);
+fn find_tag_cli(conn: &Connection, id_or_name: &str) -> SqliteResult<Tag> {
+    match get_tag_by_id_or_name(conn, id_or_name)? {
+        Some(tag) => Ok(tag),
+        None => {
+            eprintln!("Error: Tag '{}' not found", id_or_name);
+            Err(rusqlite::Error::QueryReturnedNoRows)
         }
     }
+}
 
-    let now = chrono::Utc::now().to_rfc3339();
-    let mut updates = vec!["updated_at = ?".to_string()];
-    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
+fn delete_tag_cli(conn: &Connection, id_or_name: &str) -> SqliteResult<()> {
+    let tag = find_tag_cli(conn, id_or_name)?;
 
-    // Name update
-    if let Some(new_name) = name {
-        updates.push("name = ?".to_string());
-        params.push(Box::new(new_name.to_string()));
+    // Prevent deletion of system tags, same as the GUI
+    if tag.is_system {
+        eprintln!("Error: Cannot delete system tag '{}'", tag.name);
+        return Err(rusqlite::Error::InvalidQuery);
     }
 
-    // Mode update
-    if let Some(new_mode) = mode {
-        updates.push("mode = ?".to_string());
-        params.push(Box::new(new_mode.as_str().to_string()));
-    }
+    conn.execute("DELETE FROM item_tags WHERE tag_id = ?", [&tag.id])?;
+    conn.execute("DELETE FROM tags WHERE id = ?", [&tag.id])?;
+    Ok(())
+}
 
-    // Model update
-    if clear_model {
-        updates.push("model = NULL".to_string());
-    } else if let Some(new_model) = model {
-        updates.push("model = ?".to_string());
-        params.push(Box::new(new_model.to_string()));
-    }
+fn rename_tag_cli(conn: &Connection, id_or_name: &str, new_name: &str) -> SqliteResult<()> {
+    let tag = find_tag_cli(conn, id_or_name)?;
 
-    // Prompt update
-    if clear_prompt {
-        updates.push("prompt_content = NULL".to_string());
-    } else if let Some(prompt_val) = prompt {
-        let prompt_content = if prompt_file {
-            match fs::read_to_string(prompt_val) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!("Error reading prompt file '{}': {}", prompt_val, e);
-                    return Err(rusqlite::Error::InvalidQuery);
-                }
-            }
-        } else {
-            prompt_val.to_string()
-        };
-        updates.push("prompt_content = ?".to_string());
-        params.push(Box::new(prompt_content));
+    if let Err(e) = validate_tag_name(new_name) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
     }
 
-    // Tools update
-    if clear_tools {
-        updates.push("tools_config = NULL".to_string());
-    } else if !tools.is_empty() {
-        let tools_config = parse_tools(tools)?;
-        updates.push("tools_config = ?".to_string());
-        params.push(Box::new(tools_config));
+    if let Some(existing) = get_tag_by_id_or_name(conn, new_name)? {
+        if existing.id != tag.id {
+            eprintln!("Error: Tag '{}' already exists", new_name);
+            return Err(rusqlite::Error::InvalidQuery);
+        }
     }
 
-    // Permissions update
-    if clear_permissions {
-        updates.push("permissions_config = NULL".to_string());
-    } else if !permissions.is_empty() {
-        let permissions_config = parse_permissions(permissions)?;
-        updates.push("permissions_config = ?".to_string());
-        params.push(Box::new(permissions_config));
-    }
+    conn.execute("UPDATE tags SET name = ? WHERE id = ?", [new_name, &tag.id])?;
+    Ok(())
+}
 
-    // Add agent ID as last parameter
-    params.push(Box::new(agent.id.clone()));
+fn recolor_tag_cli(conn: &Connection, id_or_name: &str, color: &str) -> SqliteResult<()> {
+    let tag = find_tag_cli(conn, id_or_name)?;
 
-    let sql = format!("UPDATE agents SET {} WHERE id = ?", updates.join(", "));
-    
-    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
-    conn.execute(&sql, params_refs.as_slice())?;
+    if let Err(e) = validate_tag_color(color) {
+        eprintln!("Error: {}", e);
+        return Err(rusqlite::Error::InvalidQuery);
+    }
 
+    conn.execute("UPDATE tags SET color = ? WHERE id = ?", [color, &tag.id])?;
     Ok(())
 }
 
-fn delete_agent_cli(conn: &Connection, id_or_name: &str) -> SqliteResult<()> {
-    let agent = get_agent_by_id_or_name(conn, id_or_name)?;
-    if agent.is_none() {
-        eprintln!("Error: Agent '{}' not found", id_or_name);
+fn set_item_tags_cli(conn: &Connection, id_or_name: &str, tags: &[String], attach: bool) -> SqliteResult<Item> {
+    let item = get_item_by_id_or_name(conn, id_or_name)?;
+    if item.is_none() {
+        eprintln!("Error: Item '{}' not found", id_or_name);
         return Err(rusqlite::Error::QueryReturnedNoRows);
     }
-    let agent = agent.unwrap();
+    let item = item.unwrap();
 
-    conn.execute("DELETE FROM agents WHERE id = ?", [&agent.id])?;
-    Ok(())
+    for tag in resolve_tags(conn, tags)? {
+        if attach {
+            conn.execute(
+                "INSERT OR IGNORE INTO item_tags (item_id, tag_id) VALUES (?, ?)",
+                [&item.id, &tag.id],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM item_tags WHERE item_id = ? AND tag_id = ?",
+                [&item.id, &tag.id],
+            )?;
+        }
+    }
+
+    Ok(item)
 }
 
-fn print_agent_json(agent: &Agent) {
-    let mut json_obj = serde_json::Map::new();
-    json_obj.insert("id".to_string(), json!(agent.id));
-    json_obj.insert("name".to_string(), json!(agent.name));
-    json_obj.insert("mode".to_string(), json!(agent.mode));
-    
-    if let Some(model) = &agent.model {
-        json_obj.insert("model".to_string(), json!(model));
+fn print_tags_json(tags: &[Tag]) {
+    let json_tags: Vec<Value> = tags
+        .iter()
+        .map(|tag| {
+            json!({
+                "id": tag.id,
+                "name": tag.name,
+                "color": tag.color,
+                "is_system": tag.is_system,
+            })
+        })
+        .collect();
+
+    println!("{}", serde_json::to_string_pretty(&json_tags).unwrap());
+}
+
+fn sync_agents(agents: &[Agent], target: AgentTarget, plan: &mut SyncPlan) -> std::io::Result<()> {
+    match target {
+        AgentTarget::Opencode => sync_agents_to_opencode(agents, plan),
+        AgentTarget::Claude => sync_agents_to_claude(agents, plan),
+        AgentTarget::Roo => sync_agents_to_modes(agents, ".roomodes", plan),
+        AgentTarget::Kilo => sync_agents_to_modes(agents, ".kilocodemodes", plan),
     }
-    
-    if let Some(prompt) = &agent.prompt_content {
-        json_obj.insert("prompt_content".to_string(), json!(prompt));
+}
+
+/// Claude Code tool names for the lowercase OpenCode-style names stored in tools_config
+fn claude_tool_name(tool: &str) -> String {
+    match tool {
+        "read" => "Read".to_string(),
+        "write" => "Write".to_string(),
+        "edit" => "Edit".to_string(),
+        "multiedit" => "MultiEdit".to_string(),
+        "bash" => "Bash".to_string(),
+        "grep" => "Grep".to_string(),
+        "glob" => "Glob".to_string(),
+        "list" => "LS".to_string(),
+        "webfetch" => "WebFetch".to_string(),
+        "websearch" | "search" => "WebSearch".to_string(),
+        "todowrite" => "TodoWrite".to_string(),
+        other => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
     }
-    
-    if let Some(tools) = &agent.tools_config {
-        if let Ok(tools_json) = serde_json::from_str::<Value>(tools) {
-            json_obj.insert("tools_config".to_string(), tools_json);
+}
+
+/// Subagent frontmatter only takes the `sonnet`, `opus` and `haiku` aliases (or
+/// `inherit`), so "anthropic/claude-sonnet-4" becomes "sonnet"
+fn claude_model_alias(model: &str) -> Option<&'static str> {
+    let (provider, name) = model.rsplit_once('/').unwrap_or(("anthropic", model));
+    if provider != "anthropic" {
+        return None;
+    }
+    let name = name.to_lowercase();
+    ["inherit", "sonnet", "opus", "haiku"]
+        .into_iter()
+        .find(|alias| name == *alias || (*alias != "inherit" && name.starts_with("claude-") && name.contains(alias)))
+}
+
+fn sync_agents_to_claude(agents: &[Agent], plan: &mut SyncPlan) -> std::io::Result<()> {
+    let agents_path = PathBuf::from(".claude/agents");
+
+    for agent in agents {
+        let prompt = agent.prompt_content.as_deref().unwrap_or_default();
+
+        // Claude picks subagents by description; fall back to the prompt's first line
+        let description = prompt
+            .lines()
+            .map(|l| l.trim().trim_start_matches('#').trim())
+            .find(|l| !l.is_empty())
+            .unwrap_or(&agent.name);
+
+        let mut frontmatter = format!("---\nname: {}\ndescription: {}\n", agent.name, yaml_scalar(description));
+
+        if let Some(tools_json) = &agent.tools_config {
+            if let Ok(Value::Object(tools)) = serde_json::from_str::<Value>(tools_json) {
+                let enabled: Vec<String> = tools
+                    .iter()
+                    .filter(|(_, v)| v.as_bool().unwrap_or(true))
+                    .map(|(k, _)| claude_tool_name(k))
+                    .collect();
+                if !enabled.is_empty() {
+                    frontmatter.push_str(&format!("tools: {}\n", enabled.join(", ")));
+                }
+            }
         }
+
+        if let Some(model) = &agent.model {
+            match claude_model_alias(model) {
+                Some(alias) => frontmatter.push_str(&format!("model: {}\n", alias)),
+                None => eprintln!(
+                    "Warning: Skipping model '{}' for agent '{}' (not a Claude model family)",
+                    model, agent.name
+                ),
+            }
+        }
+
+        frontmatter.push_str("---\n\n");
+        plan.write(
+            agents_path.join(format!("{}.md", agent.name)),
+            frontmatter + prompt,
+            vec![LockSource::agent(agent)],
+        );
     }
-    
-    if let Some(perms) = &agent.permissions_config {
-        if let Ok(perms_json) = serde_json::from_str::<Value>(perms) {
-            json_obj.insert("permissions_config".to_string(), perms_json);
+    Ok(())
+}
+
+/// Roo/Kilo tool group granting an OpenCode-style tool name
+fn roo_tool_group(tool: &str) -> Option<&'static str> {
+    match tool {
+        "read" | "grep" | "glob" | "list" => Some("read"),
+        "write" | "edit" | "multiedit" | "patch" => Some("edit"),
+        "bash" => Some("command"),
+        "webfetch" | "websearch" | "search" => Some("browser"),
+        _ => None,
+    }
+}
+
+/// Merge agents into a Roo/Kilo custom modes file (`customModes` entries keyed
+/// by slug); modes defined by hand are kept.
+fn sync_agents_to_modes(agents: &[Agent], file: &str, plan: &mut SyncPlan) -> std::io::Result<()> {
+    const ALL_GROUPS: [&str; 5] = ["read", "edit", "browser", "command", "mcp"];
+    let config_path = PathBuf::from(file);
+
+    let mut config: Value = match plan.current_content(&config_path)? {
+        Some(content) if content.trim().is_empty() => json!({}),
+        // Roo also reads YAML mode files, which would lose their comments and
+        // layout if rewritten, so those are left to the user
+        Some(content) if !content.trim_start().starts_with(['{', '[']) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{} is YAML; only JSON mode files can be updated, so convert it or add the modes by hand",
+                    file
+                ),
+            ));
+        }
+        Some(content) => serde_json::from_str(&content).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} is not valid JSON ({})", file, e),
+            )
+        })?,
+        None => json!({}),
+    };
+    let Some(object) = config.as_object_mut() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} must hold a JSON object", file),
+        ));
+    };
+    let mut modes = match object.remove("customModes") {
+        Some(Value::Array(modes)) => modes,
+        _ => Vec::new(),
+    };
+
+    for agent in agents {
+        let slug = slugify(&agent.name);
+
+        // No tools_config means every group, as with OpenCode's defaults
+        let mut groups: Vec<&str> = match agent
+            .tools_config
+            .as_deref()
+            .and_then(|json| serde_json::from_str::<Value>(json).ok())
+        {
+            Some(Value::Object(tools)) => {
+                let mut groups: Vec<&str> = Vec::new();
+                for (tool, enabled) in &tools {
+                    if let Some(group) = roo_tool_group(tool) {
+                        if enabled.as_bool().unwrap_or(true) && !groups.contains(&group) {
+                            groups.push(group);
+                        }
+                    }
+                }
+                groups
+            }
+            _ => ALL_GROUPS.to_vec(),
+        };
+
+        // Denied permissions remove the matching group
+        if let Some(Value::Object(perms)) = agent
+            .permissions_config
+            .as_deref()
+            .and_then(|json| serde_json::from_str::<Value>(json).ok())
+        {
+            for (tool, level) in &perms {
+                if level.as_str() == Some("deny") {
+                    if let Some(group) = roo_tool_group(tool) {
+                        groups.retain(|g| *g != group);
+                    }
+                }
+            }
+        }
+        groups.sort_by_key(|g| ALL_GROUPS.iter().position(|a| a == g));
+
+        let mode = json!({
+            "slug": slug,
+            "name": agent.name,
+            "roleDefinition": agent.prompt_content.as_deref().unwrap_or_default(),
+            "groups": groups,
+            "source": "project"
+        });
+
+        match modes.iter_mut().find(|m| m["slug"] == json!(slug)) {
+            Some(existing) => *existing = mode,
+            None => modes.push(mode),
         }
     }
-    
-    println!("{}", serde_json::to_string_pretty(&json_obj).unwrap());
+    object.insert("customModes".to_string(), Value::Array(modes));
+
+    let pretty_json = serde_json::to_string_pretty(&config).map_err(std::io::Error::other)?;
+    plan.write_shared(config_path, pretty_json, agents.iter().map(LockSource::agent).collect());
+    Ok(())
 }
 
-fn sync_agents_to_opencode(agents: &[Agent]) -> std::io::Result<()> {
+fn sync_agents_to_opencode(agents: &[Agent], plan: &mut SyncPlan) -> std::io::Result<()> {
     let config_path = PathBuf::from("opencode.json");
 
     // Read existing config or create new
-    let mut config: Value = if config_path.exists() {
-        let content = fs::read_to_string(&config_path)?;
-        serde_json::from_str(&content).unwrap_or_else(|_| {
+    let mut config: Value = match plan.current_content(&config_path)? {
+        Some(content) => serde_json::from_str(&content).unwrap_or_else(|_| {
             json!({
                 "$schema": "https://opencode.ai/config.json"
             })
-        })
-    } else {
-        json!({
+        }),
+        None => json!({
             "$schema": "https://opencode.ai/config.json"
-        })
+        }),
     };
 
     // Ensure agent object exists
@@ -1140,12 +5871,8 @@ fn sync_agents_to_opencode(agents: &[Agent]) -> std::io::Result<()> {
         if let Some(prompt) = &agent.prompt_content {
             // Save prompt to file
             let prompt_path = PathBuf::from(format!(".opencode/prompts/{}.txt", agent.name));
-            if let Some(parent) = prompt_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::write(&prompt_path, prompt)?;
             agent_config["prompt"] = json!(format!("{{file:{}}}", prompt_path.display()));
-            println!("  + Saved prompt to {}", prompt_path.display());
+            plan.write(prompt_path, prompt, vec![LockSource::agent(agent)]);
         }
 
         if let Some(tools_json) = &agent.tools_config {
@@ -1161,14 +5888,620 @@ fn sync_agents_to_opencode(agents: &[Agent]) -> std::io::Result<()> {
         }
 
         config["agent"][&agent.name] = agent_config;
-        println!("  + Added agent config: {}", agent.name);
     }
 
     // Write back to opencode.json
     let pretty_json = serde_json::to_string_pretty(&config)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    fs::write(&config_path, pretty_json)?;
-    println!("  + Updated opencode.json");
+    plan.write_shared(config_path, pretty_json, agents.iter().map(LockSource::agent).collect());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: &str, body: &str) -> ManagedBlock {
+        ManagedBlock {
+            id: id.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    /// A fresh directory for tests that need files on disk
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("promption-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Lockfile entry for `content` as synced to `path`
+    fn synced_entry(path: &Path, target: &str, content: &str) -> LockEntry {
+        LockEntry {
+            path: lock_path_string(path),
+            target: target.to_string(),
+            hash: lock_hash(content),
+            sources: vec![],
+            shared: false,
+        }
+    }
+
+    fn lock_of(entries: Vec<LockEntry>) -> SyncLock {
+        SyncLock {
+            version: LOCK_VERSION,
+            entries,
+        }
+    }
+
+    /// An empty, fully migrated in-memory library
+    fn library() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::apply(&conn).unwrap();
+        conn
+    }
+
+    fn add_item(conn: &Connection, name: &str, item_type: ItemTypeArg, content: &str, tags: &[&str]) -> Item {
+        let item = create_item_cli(conn, name, item_type, content, false, &ItemMetadataArgs::default()).unwrap();
+        let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+        set_item_tags_cli(conn, &item.id, &tags, true).unwrap();
+        item
+    }
+
+    #[test]
+    fn managed_blocks_keep_hand_written_text_around_them() {
+        let existing = "# Notes\n\nWritten by hand.\n\n\
+                        <!-- promption:begin id=a -->\nold a\n<!-- promption:end -->\n\n\
+                        Footer by hand.\n";
+        let updated = update_managed_blocks(existing, &[block("a", "new a")]);
+        assert_eq!(
+            updated,
+            "# Notes\n\nWritten by hand.\n\n\
+             <!-- promption:begin id=a -->\nnew a\n<!-- promption:end -->\n\n\
+             Footer by hand.\n"
+        );
+    }
+
+    #[test]
+    fn managed_blocks_drop_deselected_blocks() {
+        let existing = "Intro\n\n\
+                        <!-- promption:begin id=a -->\na\n<!-- promption:end -->\n\n\
+                        <!-- promption:begin id=b -->\nb\n<!-- promption:end -->\n\n\
+                        Outro\n";
+        let updated = update_managed_blocks(existing, &[block("b", "b")]);
+        assert_eq!(
+            updated,
+            "Intro\n\n<!-- promption:begin id=b -->\nb\n<!-- promption:end -->\n\nOutro\n"
+        );
+        assert_eq!(update_managed_blocks(&updated, &[]), "Intro\n\nOutro\n");
+    }
+
+    #[test]
+    fn managed_blocks_leave_unterminated_markers_as_text() {
+        let existing = "<!-- promption:begin id=a -->\nhalf edited\n\
+                        <!-- promption:begin id=b -->\nold b\n<!-- promption:end -->\n";
+        let updated = update_managed_blocks(existing, &[block("b", "new b")]);
+        assert_eq!(
+            updated,
+            "<!-- promption:begin id=a -->\nhalf edited\n\
+             <!-- promption:begin id=b -->\nnew b\n<!-- promption:end -->\n"
+        );
+    }
+
+    #[test]
+    fn managed_blocks_update_crlf_files_in_place() {
+        let existing = "Intro\r\n\r\n<!-- promption:begin id=a -->\r\nold\r\n<!-- promption:end -->\r\n";
+        let updated = update_managed_blocks(existing, &[block("a", "new")]);
+        assert_eq!(updated, "Intro\n\n<!-- promption:begin id=a -->\nnew\n<!-- promption:end -->\n");
+        assert_eq!(updated.matches("promption:begin").count(), 1);
+    }
+
+    const SYNCED_DOCUMENT: &str = "# Project\n\n\
+                                   <!-- promption:begin id=a -->\n## A\n\nBody\n<!-- promption:end -->\n";
+
+    #[test]
+    fn edits_outside_managed_blocks_are_not_drift() {
+        let dir = scratch_dir();
+        let path = dir.join("CLAUDE.md");
+        let entry = synced_entry(&path, "claude", SYNCED_DOCUMENT);
+
+        let edited = SYNCED_DOCUMENT.replace("# Project", "# Project\n\nOur own notes.") + "\nFooter\n";
+        fs::write(&path, edited.replace('\n', "\r\n")).unwrap();
+        assert_eq!(file_state(&entry), FileState::Ok);
+
+        fs::write(&path, SYNCED_DOCUMENT.replace("Body", "Edited body")).unwrap();
+        assert_eq!(file_state(&entry), FileState::Modified);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn prune_strips_blocks_from_documents_edited_outside_them() {
+        let dir = scratch_dir();
+        let path = dir.join("CLAUDE.md");
+        let lock = lock_of(vec![synced_entry(&path, "claude", SYNCED_DOCUMENT)]);
+        fs::write(&path, SYNCED_DOCUMENT.replace("# Project", "# Project\n\nOur own notes.")).unwrap();
+
+        let mut plan = SyncPlan::default();
+        plan_prune(&mut plan, &lock, "claude").unwrap();
+        assert!(plan.deletes.is_empty());
+        assert_eq!(plan.writes.len(), 1);
+        assert_eq!(plan.writes[0].content, "# Project\n\nOur own notes.\n");
+        assert!(!plan.writes[0].is_recorded());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clean_removes_what_promption_wrote_and_keeps_what_the_user_wrote() {
+        let dir = scratch_dir();
+        let rule = dir.join(".junie/rules.md");
+        let ours = dir.join("GEMINI.md");
+        let shared = dir.join("AGENTS.md");
+        let other = dir.join("other.md");
+        fs::create_dir_all(rule.parent().unwrap()).unwrap();
+        fs::write(&rule, "Rule").unwrap();
+        fs::write(&ours, SYNCED_DOCUMENT.replace("# Project\n\n", "")).unwrap();
+        fs::write(&shared, SYNCED_DOCUMENT.replace("# Project", "# Project\n\nOur own notes.")).unwrap();
+        fs::write(&other, "Other target").unwrap();
+        let lock = lock_of(vec![
+            synced_entry(&rule, "jetbrains", "Rule"),
+            synced_entry(&ours, "jetbrains", &SYNCED_DOCUMENT.replace("# Project\n\n", "")),
+            synced_entry(&shared, "jetbrains", SYNCED_DOCUMENT),
+            synced_entry(&other, "cursor", "Other target"),
+        ]);
+
+        let mut plan = SyncPlan::default();
+        plan_prune(&mut plan, &lock, "jetbrains").unwrap();
+        plan.apply().unwrap();
+
+        assert!(!rule.exists() && !rule.parent().unwrap().exists());
+        assert!(!ours.exists());
+        assert_eq!(fs::read_to_string(&shared).unwrap(), "# Project\n\nOur own notes.\n");
+        assert_eq!(fs::read_to_string(&other).unwrap(), "Other target");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn prune_keeps_documents_whose_blocks_were_edited() {
+        let dir = scratch_dir();
+        let path = dir.join("CLAUDE.md");
+        let lock = lock_of(vec![synced_entry(&path, "claude", SYNCED_DOCUMENT)]);
+        fs::write(&path, SYNCED_DOCUMENT.replace("Body", "Edited body")).unwrap();
+
+        let mut plan = SyncPlan::default();
+        plan_prune(&mut plan, &lock, "claude").unwrap();
+        assert!(plan.deletes.is_empty() && plan.writes.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn import_keeps_an_unterminated_block_out_of_the_next_one() {
+        let text = "<!-- promption:begin id=a -->\n# Half edited\n\n\
+                    <!-- promption:begin id=b -->\n# Style\n\nUse tabs\n<!-- promption:end -->\n";
+        let found = import_managed_document(Path::new("AGENTS.md"), text, "Agents");
+        let names: Vec<&str> = found.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["Style", "Agents"]);
+        assert_eq!(found[0].content, "Use tabs");
+        assert_eq!(found[1].content, "<!-- promption:begin id=a -->\n# Half edited");
+    }
+
+    #[test]
+    fn built_in_targets_resolve_despite_a_broken_targets_file() {
+        let dir = scratch_dir();
+        let path = dir.join(TARGETS_FILE);
+        fs::write(&path, "[targets.acme\npath = ").unwrap();
+        let load = || read_template_targets(&path);
+
+        assert!(matches!(SyncTarget::resolve_with("cursor", load), Ok(SyncTarget::Builtin(ToolTarget::Cursor))));
+        assert!(matches!(SyncTarget::resolve_with("Copilot", load), Ok(SyncTarget::Builtin(ToolTarget::Copilot))));
+        let error = SyncTarget::resolve_with("acme", load).unwrap_err();
+        assert!(error.starts_with(&format!("Invalid {}", path.display())), "{}", error);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn targets_file_can_redefine_built_in_targets() {
+        let dir = scratch_dir();
+        let path = dir.join(TARGETS_FILE);
+        fs::write(&path, "[targets.cursor]\npath = \".cursor/rules/{slug}.md\"\n").unwrap();
+        let load = || read_template_targets(&path);
+
+        match SyncTarget::resolve_with("cursor", load).unwrap() {
+            SyncTarget::Template(template) => assert_eq!(template.path.as_deref(), Some(".cursor/rules/{slug}.md")),
+            other => panic!("expected the targets.toml definition, got {:?}", other),
+        }
+        assert!(matches!(SyncTarget::resolve_with("zed", load), Ok(SyncTarget::Builtin(ToolTarget::Zed))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bundle_import_creates_only_used_tags_and_never_system_tags() {
+        let source = library();
+        create_tag_cli(&source, "react", "#61dafb").unwrap();
+        create_tag_cli(&source, "python", "#3776ab").unwrap();
+        source.execute("UPDATE tags SET is_system = 1 WHERE name = 'react'", []).unwrap();
+        let items = vec![
+            add_item(&source, "React Rules", ItemTypeArg::Rule, "Use hooks", &["react"]),
+            add_item(&source, "Python Rules", ItemTypeArg::Rule, "Use black", &["python"]),
+        ];
+        let bundle = build_bundle(&source, &items, &[]).unwrap();
+
+        let target = library();
+        add_item(&target, "Python Rules", ItemTypeArg::Rule, "Use ruff", &[]);
+        let tag_rows = |conn: &Connection| -> Vec<(String, bool)> {
+            let mut stmt = conn
+                .prepare("SELECT name, is_system FROM tags WHERE name IN ('react', 'python') ORDER BY name")
+                .unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        import_bundle_cli(&target, &bundle, ConflictPolicy::Skip, false).unwrap();
+        assert_eq!(tag_rows(&target), [("react".to_string(), false)]);
+
+        import_bundle_cli(&target, &bundle, ConflictPolicy::Rename, false).unwrap();
+        assert_eq!(tag_rows(&target), [("python".to_string(), false), ("react".to_string(), false)]);
+        for (name, tag) in [("React Rules 2", "react"), ("Python Rules 2", "python")] {
+            let item = get_item_by_id_or_name(&target, name).unwrap().unwrap();
+            let tags: Vec<String> = get_item_tags(&target, &item.id).unwrap().into_iter().map(|t| t.name).collect();
+            assert_eq!(tags, [tag]);
+        }
+    }
+
+    #[test]
+    fn lock_round_trips_through_json() {
+        let item = Item {
+            id: "i1".to_string(),
+            name: "React Rules".to_string(),
+            content: "Use hooks".to_string(),
+            item_type: "rule".to_string(),
+            metadata: ItemMetadata::default(),
+        };
+        let mut shared = synced_entry(Path::new("opencode.json"), "opencode-agents", "{}");
+        shared.shared = true;
+        shared.sources = vec![LockSource::item(&item)];
+        let lock = lock_of(vec![synced_entry(Path::new(".cursor/rules/a.mdc"), "cursor", "A"), shared]);
+
+        let json = serde_json::to_string_pretty(&lock).unwrap();
+        assert_eq!(json.matches("\"shared\"").count(), 1);
+        let parsed: SyncLock = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&parsed).unwrap(), json);
+        assert!(!parsed.entries[0].shared && parsed.entries[1].shared);
+        assert_eq!(parsed.entries[1].sources, [LockSource::item(&item)]);
+    }
+
+    #[test]
+    fn lock_record_replaces_files_and_merges_shared_sources() {
+        let source = |id: &str| LockSource {
+            kind: "agent".to_string(),
+            id: id.to_string(),
+            name: id.to_string(),
+            hash: content_hash(id),
+        };
+        let entry = |path: &str, shared: bool, sources: Vec<LockSource>| LockEntry {
+            sources,
+            shared,
+            ..synced_entry(Path::new(path), "opencode-agents", path)
+        };
+        let mut lock = lock_of(vec![]);
+
+        lock.record(entry("a.md", false, vec![source("a")]));
+        lock.record(entry("a.md", false, vec![source("b")]));
+        lock.record(entry("opencode.json", true, vec![source("a")]));
+        lock.record(entry("opencode.json", true, vec![source("b")]));
+
+        assert_eq!(lock.entries.len(), 2);
+        assert_eq!(lock.entries[0].sources, [source("b")]);
+        assert_eq!(lock.entries[1].sources, [source("b"), source("a")]);
+    }
+
+    #[test]
+    fn status_reports_file_and_library_drift() {
+        let dir = scratch_dir();
+        let conn = library();
+        let kept = add_item(&conn, "Kept", ItemTypeArg::Rule, "Kept", &[]);
+        let changed = add_item(&conn, "Changed", ItemTypeArg::Rule, "Changed", &[]);
+        let removed = add_item(&conn, "Removed", ItemTypeArg::Rule, "Removed", &[]);
+
+        let mut entries = Vec::new();
+        for (name, item) in [("unchanged.md", &kept), ("modified.md", &changed), ("missing.md", &removed)] {
+            let path = dir.join(name);
+            fs::write(&path, &item.content).unwrap();
+            let mut entry = synced_entry(&path, "cursor", &item.content);
+            entry.sources = vec![LockSource::item(item)];
+            entries.push(entry);
+        }
+        fs::write(dir.join("modified.md"), "Edited by hand").unwrap();
+        fs::remove_file(dir.join("missing.md")).unwrap();
+        conn.execute("UPDATE items SET content = 'Changed again' WHERE id = ?", [&changed.id])
+            .unwrap();
+        delete_item_cli(&conn, &removed.id).unwrap();
+
+        let statuses: Vec<EntryStatus> = entries.iter().map(|e| check_lock_entry(&conn, e).unwrap()).collect();
+        let labels: Vec<&str> = statuses.iter().map(EntryStatus::label).collect();
+        assert_eq!(labels, ["ok", "modified", "deleted"]);
+        assert!(statuses[0].is_clean());
+        assert_eq!(statuses[1].outdated, ["Changed"]);
+        assert_eq!(statuses[2].removed, ["Removed"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn names<T>(entries: &[T], name: impl Fn(&T) -> &str) -> Vec<String> {
+        entries.iter().map(|e| name(e).to_string()).collect()
+    }
+
+    #[test]
+    fn item_selectors_combine_ids_names_and_globs() {
+        let conn = library();
+        let rules = add_item(&conn, "React Rules", ItemTypeArg::Rule, "Rules", &[]);
+        add_item(&conn, "React Hooks", ItemTypeArg::Skill, "Hooks", &[]);
+        add_item(&conn, "Git Workflow", ItemTypeArg::Workflow, "Git", &[]);
+
+        let select = ItemSelectors {
+            ids: vec![rules.id.clone(), "nope".to_string()],
+            names: vec!["git WORKFLOW".to_string(), "react rules".to_string(), "Missing".to_string()],
+            patterns: vec!["react-h*".to_string(), "vue-*".to_string()],
+            ..Default::default()
+        };
+        let (items, unmatched) = resolve_item_selectors(&conn, &select).unwrap();
+        assert_eq!(names(&items, |i| &i.name), ["React Rules", "Git Workflow", "React Hooks"]);
+        assert_eq!(unmatched, ["--ids nope", "--names 'Missing'", "--match 'vue-*'"]);
+    }
+
+    #[test]
+    fn item_selectors_filter_by_tags() {
+        let conn = library();
+        create_tag_cli(&conn, "react", "#61dafb").unwrap();
+        create_tag_cli(&conn, "typescript", "#3178c6").unwrap();
+        add_item(&conn, "React Rules", ItemTypeArg::Rule, "Rules", &["react"]);
+        add_item(&conn, "React Hooks", ItemTypeArg::Skill, "Hooks", &["react", "typescript"]);
+        add_item(&conn, "Git Workflow", ItemTypeArg::Workflow, "Git", &[]);
+        let by_tags = |tags: &[&str], tag_mode: TagMatch, item_type: Option<ItemTypeArg>| {
+            let select = ItemSelectors {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                tag_mode,
+                item_type,
+                ..Default::default()
+            };
+            let (items, unmatched) = resolve_item_selectors(&conn, &select).unwrap();
+            (names(&items, |i| &i.name), unmatched)
+        };
+
+        let (mut any, unmatched) = by_tags(&["REACT"], TagMatch::Any, None);
+        any.sort();
+        assert_eq!((any, unmatched), (vec!["React Hooks".to_string(), "React Rules".to_string()], vec![]));
+        assert_eq!(by_tags(&["react", "typescript"], TagMatch::All, None).0, ["React Hooks"]);
+        let (items, unmatched) = by_tags(&["react"], TagMatch::Any, Some(ItemTypeArg::Workflow));
+        assert!(items.is_empty());
+        assert_eq!(unmatched, ["--tag react"]);
+    }
+
+    #[test]
+    fn agent_selectors_combine_ids_names_and_globs() {
+        let conn = library();
+        let agent = |name: &str| {
+            create_agent_cli(&conn, name, AgentModeArg::Subagent, None, Some("Prompt"), false, &[], &[]).unwrap()
+        };
+        let reviewer = agent("reviewer");
+        agent("planner");
+        agent("review-docs");
+
+        let select = AgentSelectors {
+            ids: vec![reviewer.id.clone()],
+            names: vec!["planner".to_string(), "ghost".to_string()],
+            patterns: vec!["review*".to_string(), "zzz-*".to_string()],
+            ..Default::default()
+        };
+        let (agents, unmatched) = resolve_agent_selectors(&conn, &select).unwrap();
+        assert_eq!(names(&agents, |a| &a.name), ["reviewer", "planner", "review-docs"]);
+        assert_eq!(unmatched, ["--names ghost", "--match 'zzz-*'"]);
+
+        let all = AgentSelectors {
+            all: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve_agent_selectors(&conn, &all).unwrap().0.len(), 3);
+    }
+
+    #[test]
+    fn fts_queries_quote_every_term_as_a_prefix() {
+        assert_eq!(build_fts_query("react-hooks"), "\"react-hooks\"*");
+        assert_eq!(build_fts_query("  error   handling "), "\"error\"* \"handling\"*");
+        assert_eq!(build_fts_query("say \"hi\" OR"), "\"say\"* \"\"\"hi\"\"\"* \"OR\"*");
+        assert_eq!(build_fts_query("   "), "");
+    }
+
+    fn search(conn: &Connection, query: &str) -> Vec<String> {
+        let hits = search_library(conn, &build_fts_query(query), None, ("[", "]")).unwrap();
+        names(&hits, |hit| &hit.name)
+    }
+
+    #[test]
+    fn search_finds_punctuated_and_quoted_terms() {
+        let conn = library();
+        add_item(&conn, "React Hooks", ItemTypeArg::Skill, "Prefer react-hooks over classes", &[]);
+        add_item(&conn, "Quoting", ItemTypeArg::Rule, "Never write \"TODO\" without an owner", &[]);
+
+        assert_eq!(search(&conn, "react-hoo"), ["React Hooks"]);
+        assert_eq!(search(&conn, "\"todo"), ["Quoting"]);
+        assert_eq!(search(&conn, "AND OR NOT"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn search_index_follows_items_and_agents() {
+        let conn = library();
+        let item = add_item(&conn, "Deploy Steps", ItemTypeArg::Workflow, "Run the release checklist", &[]);
+        add_item(&conn, "Checklist", ItemTypeArg::Rule, "Keep it short", &[]);
+        // Name matches rank above content matches
+        assert_eq!(search(&conn, "checklist"), ["Checklist", "Deploy Steps"]);
+
+        conn.execute("UPDATE items SET name = 'Ship It' WHERE id = ?", [&item.id]).unwrap();
+        assert_eq!(search(&conn, "deploy"), Vec::<String>::new());
+        assert_eq!(search(&conn, "ship"), ["Ship It"]);
+        delete_item_cli(&conn, &item.id).unwrap();
+        assert_eq!(search(&conn, "ship"), Vec::<String>::new());
+
+        let agent = create_agent_cli(&conn, "auditor", AgentModeArg::Subagent, None, Some("Audit logs"), false, &[], &[])
+            .unwrap();
+        assert_eq!(search(&conn, "audit"), ["auditor"]);
+        conn.execute("UPDATE agents SET prompt_content = 'Read traces' WHERE id = ?", [&agent.id])
+            .unwrap();
+        assert_eq!(search(&conn, "logs"), Vec::<String>::new());
+        assert_eq!(search(&conn, "traces"), ["auditor"]);
+        delete_agent_cli(&conn, &agent.id).unwrap();
+        assert_eq!(search(&conn, "auditor"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn target_overrides_win_over_base_metadata() {
+        let metadata: ItemMetadata = serde_json::from_str(
+            r#"{"activation": "glob", "globs": ["src/**"], "description": "Base",
+                "targets": {"cursor": {"activation": "manual"}, "windsurf": {"globs": ["docs/**"]}}}"#,
+        )
+        .unwrap();
+
+        let cursor = metadata.for_target(ToolTarget::Cursor);
+        assert_eq!(cursor.activation, Some(Activation::Manual));
+        assert_eq!(cursor.globs, ["src/**"]);
+        assert_eq!(cursor.description.as_deref(), Some("Base"));
+        assert!(cursor.targets.is_empty());
+
+        let windsurf = metadata.for_target(ToolTarget::Windsurf);
+        assert_eq!(windsurf.activation, Some(Activation::Glob));
+        assert_eq!(windsurf.globs, ["docs/**"]);
+        assert_eq!(metadata.for_target_name("acme"), ItemMetadata { targets: BTreeMap::new(), ..metadata.clone() });
+    }
+
+    #[test]
+    fn metadata_column_parses_empty_and_unreadable_json_as_no_metadata() {
+        for json in [None, Some("{}"), Some("not json"), Some("{\"activation\": \"sometimes\"}")] {
+            assert!(ItemMetadata::parse(json.map(str::to_string)).is_empty(), "{:?}", json);
+        }
+        assert_eq!(metadata_to_sql(&ItemMetadata::default()).unwrap(), None);
+
+        let mut metadata = ItemMetadata {
+            globs: vec!["*.rs".to_string()],
+            ..Default::default()
+        };
+        metadata.targets.insert("cursor".to_string(), ItemMetadata {
+            activation: Some(Activation::Model),
+            ..Default::default()
+        });
+        let json = metadata_to_sql(&metadata).unwrap().unwrap();
+        assert_eq!(json, r#"{"globs":["*.rs"],"targets":{"cursor":{"activation":"model"}}}"#);
+        assert_eq!(ItemMetadata::parse(Some(json)), metadata);
+
+        let conn = library();
+        let item = add_item(&conn, "Plain", ItemTypeArg::Rule, "Plain", &[]);
+        conn.execute("UPDATE items SET metadata = '{}' WHERE id = ?", [&item.id]).unwrap();
+        assert!(get_item_by_id_or_name(&conn, &item.id).unwrap().unwrap().metadata.is_empty());
+    }
+
+    #[test]
+    fn mode_files_are_updated_as_json_and_refused_as_yaml() {
+        let conn = library();
+        let agent = create_agent_cli(&conn, "architect", AgentModeArg::Primary, None, Some("Plan"), false, &[], &[])
+            .unwrap();
+        let mode_file = |existing: &str| {
+            let mut plan = SyncPlan::default();
+            plan.write(".roomodes", existing, vec![]);
+            sync_agents_to_modes(std::slice::from_ref(&agent), ".roomodes", &mut plan).map(|_| plan)
+        };
+
+        let plan = mode_file("{\"customModes\": [{\"slug\": \"docs\"}]}").unwrap();
+        let config: Value = serde_json::from_str(&plan.writes[0].content).unwrap();
+        assert_eq!(config["customModes"][0]["slug"], "docs");
+        assert_eq!(config["customModes"][1]["slug"], "architect");
+        assert!(mode_file("").is_ok());
+
+        let error = mode_file("customModes:\n  - slug: docs\n").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with(".roomodes is YAML"), "{}", error);
+        assert!(mode_file("[]").unwrap_err().to_string().contains("must hold a JSON object"));
+    }
+
+    #[test]
+    fn claude_agent_models_become_aliases() {
+        for (model, alias) in [
+            ("anthropic/claude-sonnet-4", Some("sonnet")),
+            ("claude-opus-4-1-20250805", Some("opus")),
+            ("anthropic/claude-3-5-haiku-latest", Some("haiku")),
+            ("Sonnet", Some("sonnet")),
+            ("inherit", Some("inherit")),
+            ("openai/gpt-4o", None),
+            ("gpt-4", None),
+            ("bedrock/claude-sonnet-4", None),
+        ] {
+            assert_eq!(claude_model_alias(model), alias, "{}", model);
+        }
+    }
+
+    #[test]
+    fn install_prunes_targets_dropped_from_the_manifest() {
+        let lock = lock_of(vec![
+            synced_entry(Path::new(".cursor/rules/a.mdc"), "cursor", "a"),
+            synced_entry(Path::new("CLAUDE.md"), "claude", "b"),
+            synced_entry(Path::new(".claude/commands/c.md"), "claude", "c"),
+            synced_entry(Path::new(".roo/rules-architect/d.md"), "roo:architect", "d"),
+            synced_entry(Path::new(".claude/agents/e.md"), "claude-agents", "e"),
+        ]);
+        let listed = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            dropped_lock_targets(&lock, &listed(&["cursor", "claude-agents"])),
+            ["claude", "roo:architect"]
+        );
+        assert_eq!(dropped_lock_targets(&lock, &listed(&["claude", "roo"])), ["cursor", "claude-agents"]);
+        assert!(dropped_lock_targets(&lock, &listed(&["cursor", "claude", "roo", "claude-agents"])).is_empty());
+    }
+
+    #[test]
+    fn aider_config_adds_missing_read_key() {
+        assert_eq!(aider_config_with_read("", "CONVENTIONS.md"), "read:\n  - CONVENTIONS.md\n");
+        assert_eq!(
+            aider_config_with_read("model: sonnet", "CONVENTIONS.md"),
+            "model: sonnet\nread:\n  - CONVENTIONS.md\n"
+        );
+    }
+
+    #[test]
+    fn aider_config_extends_block_list_and_keeps_other_keys() {
+        let config = "# my settings\nread:\n    - NOTES.md # team notes\nauto-commits: false\n";
+        assert_eq!(
+            aider_config_with_read(config, "CONVENTIONS.md"),
+            "# my settings\nread:\n    - NOTES.md # team notes\n    - CONVENTIONS.md\nauto-commits: false\n"
+        );
+    }
+
+    #[test]
+    fn aider_config_extends_inline_list_and_scalar() {
+        assert_eq!(
+            aider_config_with_read("read: [NOTES.md]\n", "CONVENTIONS.md"),
+            "read: [NOTES.md, CONVENTIONS.md]\n"
+        );
+        assert_eq!(
+            aider_config_with_read("read: NOTES.md\n", "CONVENTIONS.md"),
+            "read:\n  - NOTES.md\n  - CONVENTIONS.md\n"
+        );
+    }
+
+    #[test]
+    fn aider_config_is_unchanged_when_already_read() {
+        for config in [
+            "read:\n  - \"CONVENTIONS.md\"\n",
+            "read: ['CONVENTIONS.md', NOTES.md]\n",
+            "read: CONVENTIONS.md # ours\n",
+        ] {
+            assert_eq!(aider_config_with_read(config, "CONVENTIONS.md"), config);
+        }
+    }
+
+    #[test]
+    fn aider_config_keeps_crlf_line_endings() {
+        assert_eq!(
+            aider_config_with_read("model: sonnet\r\nread:\r\n  - NOTES.md\r\n", "CONVENTIONS.md"),
+            "model: sonnet\r\nread:\r\n  - NOTES.md\r\n  - CONVENTIONS.md\r\n"
+        );
+    }
+}
@@ -1,81 +1,65 @@
 use tauri_plugin_sql::{Migration, MigrationKind};
 
 pub mod cli;
+mod migrations;
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    let migrations = vec![
-        Migration {
-            version: 1,
-            description: "Create initial tables",
-            sql: r#"
-                CREATE TABLE IF NOT EXISTS items (
-                    id TEXT PRIMARY KEY NOT NULL,
-                    name TEXT NOT NULL,
-                    content TEXT NOT NULL,
-                    item_type TEXT NOT NULL CHECK(item_type IN ('skill', 'rule', 'workflow')),
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
+#[tauri::command]
+fn database_url() -> String {
+    cli::gui_database_url()
+}
 
-                CREATE TABLE IF NOT EXISTS tags (
-                    id TEXT PRIMARY KEY NOT NULL,
-                    name TEXT NOT NULL UNIQUE,
-                    color TEXT NOT NULL DEFAULT '#6366f1'
-                );
+#[tauri::command]
+fn current_library() -> Option<String> {
+    cli::gui_library()
+}
 
-                CREATE TABLE IF NOT EXISTS item_tags (
-                    item_id TEXT NOT NULL,
-                    tag_id TEXT NOT NULL,
-                    PRIMARY KEY (item_id, tag_id),
-                    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
-                    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
-                );
+#[tauri::command]
+fn list_libraries() -> Result<Vec<String>, String> {
+    cli::list_libraries()
+}
 
-                CREATE INDEX IF NOT EXISTS idx_items_type ON items(item_type);
-                CREATE INDEX IF NOT EXISTS idx_items_name ON items(name);
-            "#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 2,
-            description: "Add is_system field to tags",
-            sql: r#"
-                ALTER TABLE tags ADD COLUMN is_system INTEGER NOT NULL DEFAULT 0;
-            "#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 3,
-            description: "Create agents table",
-            sql: r#"
-                CREATE TABLE IF NOT EXISTS agents (
-                    id TEXT PRIMARY KEY NOT NULL,
-                    name TEXT NOT NULL UNIQUE,
-                    mode TEXT NOT NULL CHECK(mode IN ('primary', 'subagent')) DEFAULT 'subagent',
-                    model TEXT,
-                    prompt_content TEXT,
-                    tools_config TEXT,
-                    permissions_config TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
+/// The SQL plugin binds migrations to one database at startup, so switching
+/// relaunches the app on the other library.
+#[tauri::command]
+fn switch_library(app: tauri::AppHandle, name: Option<String>) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut command = std::process::Command::new(exe);
+    command.env_remove("PROMPTION_DB");
+    if let Some(name) = name {
+        command.arg("--library").arg(name);
+    }
+    command.spawn().map_err(|e| e.to_string())?;
+    app.exit(0);
+    Ok(())
+}
 
-                CREATE INDEX IF NOT EXISTS idx_agents_name ON agents(name);
-            "#,
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let migrations: Vec<Migration> = migrations::MIGRATIONS
+        .iter()
+        .map(|m| Migration {
+            version: m.version,
+            description: m.description,
+            sql: m.sql,
             kind: MigrationKind::Up,
-        },
-    ];
+        })
+        .collect();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(
             tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:promption.db", migrations)
+                .add_migrations(&cli::gui_database_url(), migrations)
                 .build(),
         )
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .invoke_handler(tauri::generate_handler![
+            database_url,
+            current_library,
+            list_libraries,
+            switch_library
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,323 @@
+//! Database schema shared by the desktop app (through `tauri_plugin_sql`) and
+//! the CLI (through `rusqlite`).
+//!
+//! Both record applied versions in sqlx's `_sqlx_migrations` table with the same
+//! checksums, so whichever side opens a library first upgrades it and the other
+//! accepts the result. Never edit a released migration; append a new one.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha384};
+
+#[derive(Debug)]
+pub struct SchemaMigration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[SchemaMigration] = &[
+    SchemaMigration {
+        version: 1,
+        description: "Create initial tables",
+        sql: r#"
+                CREATE TABLE IF NOT EXISTS items (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    name TEXT NOT NULL,
+                    content TEXT NOT NULL,
+                    item_type TEXT NOT NULL CHECK(item_type IN ('skill', 'rule', 'workflow')),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS tags (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    name TEXT NOT NULL UNIQUE,
+                    color TEXT NOT NULL DEFAULT '#6366f1'
+                );
+
+                CREATE TABLE IF NOT EXISTS item_tags (
+                    item_id TEXT NOT NULL,
+                    tag_id TEXT NOT NULL,
+                    PRIMARY KEY (item_id, tag_id),
+                    FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+                    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_items_type ON items(item_type);
+                CREATE INDEX IF NOT EXISTS idx_items_name ON items(name);
+            "#,
+    },
+    SchemaMigration {
+        version: 2,
+        description: "Add is_system field to tags",
+        sql: r#"
+                ALTER TABLE tags ADD COLUMN is_system INTEGER NOT NULL DEFAULT 0;
+            "#,
+    },
+    SchemaMigration {
+        version: 3,
+        description: "Create agents table",
+        sql: r#"
+                CREATE TABLE IF NOT EXISTS agents (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    name TEXT NOT NULL UNIQUE,
+                    mode TEXT NOT NULL CHECK(mode IN ('primary', 'subagent')) DEFAULT 'subagent',
+                    model TEXT,
+                    prompt_content TEXT,
+                    tools_config TEXT,
+                    permissions_config TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_agents_name ON agents(name);
+            "#,
+    },
+    SchemaMigration {
+        version: 4,
+        description: "Create full-text search index",
+        sql: r#"
+                CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+                    entity_id UNINDEXED,
+                    entity_type UNINDEXED,
+                    name,
+                    content,
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );
+
+                INSERT INTO search_index (entity_id, entity_type, name, content)
+                    SELECT id, item_type, name, content FROM items;
+                INSERT INTO search_index (entity_id, entity_type, name, content)
+                    SELECT id, 'agent', name, COALESCE(prompt_content, '') FROM agents;
+
+                CREATE TRIGGER IF NOT EXISTS items_search_insert AFTER INSERT ON items BEGIN
+                    INSERT INTO search_index (entity_id, entity_type, name, content)
+                        VALUES (new.id, new.item_type, new.name, new.content);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS items_search_update AFTER UPDATE ON items BEGIN
+                    DELETE FROM search_index WHERE entity_id = old.id AND entity_type <> 'agent';
+                    INSERT INTO search_index (entity_id, entity_type, name, content)
+                        VALUES (new.id, new.item_type, new.name, new.content);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS items_search_delete AFTER DELETE ON items BEGIN
+                    DELETE FROM search_index WHERE entity_id = old.id AND entity_type <> 'agent';
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS agents_search_insert AFTER INSERT ON agents BEGIN
+                    INSERT INTO search_index (entity_id, entity_type, name, content)
+                        VALUES (new.id, 'agent', new.name, COALESCE(new.prompt_content, ''));
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS agents_search_update AFTER UPDATE ON agents BEGIN
+                    DELETE FROM search_index WHERE entity_id = old.id AND entity_type = 'agent';
+                    INSERT INTO search_index (entity_id, entity_type, name, content)
+                        VALUES (new.id, 'agent', new.name, COALESCE(new.prompt_content, ''));
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS agents_search_delete AFTER DELETE ON agents BEGIN
+                    DELETE FROM search_index WHERE entity_id = old.id AND entity_type = 'agent';
+                END;
+            "#,
+    },
+    SchemaMigration {
+        version: 5,
+        description: "Add metadata field to items",
+        sql: r#"
+                ALTER TABLE items ADD COLUMN metadata TEXT;
+            "#,
+    },
+];
+
+/// Latest schema version known to this build
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Bring the schema up to date, returning the migrations that were applied.
+pub fn apply(conn: &Connection) -> Result<Vec<&'static SchemaMigration>, String> {
+    // Same layout sqlx creates, so the app sees migrations the CLI ran
+    conn.execute_batch(
+        r#"
+            CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                success BOOLEAN NOT NULL,
+                checksum BLOB NOT NULL,
+                execution_time BIGINT NOT NULL
+            );
+        "#,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let dirty: Option<i64> = conn
+        .query_row(
+            "SELECT version FROM _sqlx_migrations WHERE success = false ORDER BY version LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(version) = dirty {
+        return Err(format!("migration {} was left partially applied", version));
+    }
+
+    // Refuse before touching anything a newer build may have laid out differently
+    let newest: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM _sqlx_migrations", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if let Some(newest) = newest.filter(|&v| v > latest_version()) {
+        return Err(format!(
+            "database schema version {} is newer than this promption supports ({}); please upgrade",
+            newest,
+            latest_version()
+        ));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS {
+        let checksum = Sha384::digest(migration.sql.as_bytes()).to_vec();
+        let recorded: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT checksum FROM _sqlx_migrations WHERE version = ?",
+                [migration.version],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match recorded {
+            Some(recorded) if recorded == checksum => continue,
+            Some(_) => {
+                return Err(format!(
+                    "migration {} was applied with different SQL than this build has",
+                    migration.version
+                ))
+            }
+            None => {}
+        }
+
+        let started = std::time::Instant::now();
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch(migration.sql)
+            .map_err(|e| format!("migration {} ({}) failed: {}", migration.version, migration.description, e))?;
+        tx.execute(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+             VALUES (?, ?, true, ?, ?)",
+            params![
+                migration.version,
+                migration.description,
+                checksum,
+                started.elapsed().as_nanos() as i64
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-384 checksums sqlx 0.8 records for each migration, which the desktop
+    /// app compares on startup. A changed value means a released migration was edited.
+    const SQLX_CHECKSUMS: [&str; 5] = [
+        "5ba614977bdfe19e21611e03718b58abf79de6ad64b4359637ca9181537716224c0ca1cfdbf5489668121979c26fedc0",
+        "d68a260f71b16e3111901708258cf820160d2ebdcc1d1a525c8e579a76e0af33d26d99e327533cd78b777fd47d974e09",
+        "2712b41b53f45b5f10f5fc5025210fcee68f2ac549b762c776f9e94005462781c3d3d2d583c294a2cdcbc5a42633110e",
+        "3a9c4186e491dd082ecc94288be049fb89169d27541ce033c8c52896fd887d019fa8f246b7f62270cda165badd246289",
+        "3c9158691bb70525bfff224d237b50be4efa9219dd165614087f8da0fb19fcf5c27d3768f3b01b104ac849d661da16b9",
+    ];
+
+    fn recorded(conn: &Connection) -> Vec<(i64, bool, String)> {
+        let mut stmt = conn
+            .prepare("SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version")
+            .unwrap();
+        stmt.query_map([], |row| {
+            let checksum: Vec<u8> = row.get(2)?;
+            Ok((row.get(0)?, row.get(1)?, checksum.iter().map(|b| format!("{:02x}", b)).collect()))
+        })
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+    }
+
+    fn has_table(conn: &Connection, name: &str) -> bool {
+        conn.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?", [name], |_| Ok(()))
+            .optional()
+            .unwrap()
+            .is_some()
+    }
+
+    /// A library that some other build already recorded `version` in
+    fn recorded_by_other_build(version: i64, success: bool) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT NOT NULL, \
+             installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, success BOOLEAN NOT NULL, \
+             checksum BLOB NOT NULL, execution_time BIGINT NOT NULL);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+             VALUES (?, 'other build', ?, x'00', 0)",
+            params![version, success],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn fresh_database_records_what_sqlx_would() {
+        let conn = Connection::open_in_memory().unwrap();
+        let applied = apply(&conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(has_table(&conn, "items") && has_table(&conn, "agents"));
+
+        let expected: Vec<(i64, bool, String)> = MIGRATIONS
+            .iter()
+            .zip(SQLX_CHECKSUMS)
+            .map(|(m, checksum)| (m.version, true, checksum.to_string()))
+            .collect();
+        assert_eq!(recorded(&conn), expected);
+    }
+
+    #[test]
+    fn rerunning_applies_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply(&conn).unwrap();
+        let before = recorded(&conn);
+        assert!(apply(&conn).unwrap().is_empty());
+        assert_eq!(recorded(&conn), before);
+    }
+
+    #[test]
+    fn newer_schema_is_refused_before_anything_is_applied() {
+        let conn = recorded_by_other_build(latest_version() + 1, true);
+        let error = apply(&conn).unwrap_err();
+        assert!(error.contains("newer than this promption supports"), "{}", error);
+        assert!(!has_table(&conn, "items"));
+        assert_eq!(recorded(&conn).len(), 1);
+    }
+
+    #[test]
+    fn dirty_schema_is_refused_before_anything_is_applied() {
+        let conn = recorded_by_other_build(1, false);
+        let error = apply(&conn).unwrap_err();
+        assert!(error.contains("partially applied"), "{}", error);
+        assert!(!has_table(&conn, "items"));
+    }
+
+    #[test]
+    fn edited_migrations_are_refused() {
+        let conn = recorded_by_other_build(1, true);
+        let error = apply(&conn).unwrap_err();
+        assert!(error.contains("different SQL"), "{}", error);
+        assert!(!has_table(&conn, "items"));
+    }
+}
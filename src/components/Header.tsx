@@ -4,6 +4,7 @@ import { Button } from '@/components/ui/button';
 import { ThemeToggle } from './ThemeToggle';
 import { ZoomControls } from './ZoomControls';
 import { KeyboardShortcutsHelp } from './KeyboardShortcutsHelp';
+import { LibrarySwitcher } from './LibrarySwitcher';
 import { Tabs, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { useState, useEffect } from 'react';
 
@@ -85,6 +86,7 @@ export function Header() {
             </div>
 
             <div className="flex items-center gap-2">
+                <LibrarySwitcher />
                 <Button
                     variant="ghost"
                     size="icon"
@@ -0,0 +1,77 @@
+import { Library } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { Button } from '@/components/ui/button';
+import {
+    DropdownMenu,
+    DropdownMenuContent,
+    DropdownMenuLabel,
+    DropdownMenuRadioGroup,
+    DropdownMenuRadioItem,
+    DropdownMenuSeparator,
+    DropdownMenuTrigger,
+} from '@/components/ui/dropdown-menu';
+import { getCurrentLibrary, listLibraries, switchLibrary } from '../services/database';
+
+const DEFAULT_LIBRARY = 'default';
+
+export function LibrarySwitcher() {
+    const [libraries, setLibraries] = useState<string[]>([]);
+    const [current, setCurrent] = useState<string | null>(null);
+
+    useEffect(() => {
+        const load = async () => {
+            try {
+                setLibraries(await listLibraries());
+                setCurrent(await getCurrentLibrary());
+            } catch (error) {
+                console.error('Failed to load libraries:', error);
+            }
+        };
+        load();
+    }, []);
+
+    // Nothing to switch to until a named library exists (`promption --library NAME`)
+    if (libraries.length === 0) {
+        return null;
+    }
+
+    const handleSwitch = async (value: string) => {
+        const name = value === DEFAULT_LIBRARY ? null : value;
+        if (name === current) {
+            return;
+        }
+        try {
+            await switchLibrary(name);
+        } catch (error) {
+            console.error('Failed to switch library:', error);
+        }
+    };
+
+    return (
+        <DropdownMenu>
+            <DropdownMenuTrigger asChild>
+                <Button
+                    variant="ghost"
+                    size="sm"
+                    className="h-8 gap-1.5 text-xs text-muted-foreground hover:text-foreground"
+                    title="Switch library"
+                >
+                    <Library size={14} />
+                    {current ?? DEFAULT_LIBRARY}
+                </Button>
+            </DropdownMenuTrigger>
+            <DropdownMenuContent align="end">
+                <DropdownMenuLabel>Library</DropdownMenuLabel>
+                <DropdownMenuSeparator />
+                <DropdownMenuRadioGroup value={current ?? DEFAULT_LIBRARY} onValueChange={handleSwitch}>
+                    <DropdownMenuRadioItem value={DEFAULT_LIBRARY}>{DEFAULT_LIBRARY}</DropdownMenuRadioItem>
+                    {libraries.map((name) => (
+                        <DropdownMenuRadioItem key={name} value={name}>
+                            {name}
+                        </DropdownMenuRadioItem>
+                    ))}
+                </DropdownMenuRadioGroup>
+            </DropdownMenuContent>
+        </DropdownMenu>
+    );
+}
@@ -1,4 +1,5 @@
 import Database from '@tauri-apps/plugin-sql';
+import { invoke } from '@tauri-apps/api/core';
 import { v4 as uuidv4 } from 'uuid';
 import type { Item, Tag, ItemType, ItemFormData, Agent, AgentFormData } from '../types';
 import { POPULAR_TECHNOLOGIES } from '../constants/technologies';
@@ -42,7 +43,9 @@ let db: Database | null = null;
 export async function getDb(): Promise<Database> {
     if (!db) {
         try {
-            db = await Database.load('sqlite:promption.db');
+            // The backend picks the library (--library, --db or PROMPTION_DB)
+            const url = await invoke<string>('database_url');
+            db = await Database.load(url);
             console.log('Database connection established');
             
             // Initialize system tags on first load
@@ -57,6 +60,22 @@ export async function getDb(): Promise<Database> {
     return db;
 }
 
+// Libraries: the default one plus named ones under the app's libraries/ directory
+
+export async function listLibraries(): Promise<string[]> {
+    return invoke<string[]>('list_libraries');
+}
+
+/** Name of the open library, or null for the default one */
+export async function getCurrentLibrary(): Promise<string | null> {
+    return invoke<string | null>('current_library');
+}
+
+/** Relaunches the app on another library (null for the default one) */
+export async function switchLibrary(name: string | null): Promise<void> {
+    await invoke('switch_library', { name });
+}
+
 // Initialize system technology tags
 async function initializeSystemTags(): Promise<void> {
     try {